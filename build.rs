@@ -1,26 +1,147 @@
 use std::{
     env,
     fs::{read, write},
-    path::Path,
+    path::{Path, PathBuf},
 };
 
+const HEADER_SIZE: usize = 0x40;
+const IPL3_SIZE: usize = 0x1000 - HEADER_SIZE;
+
+// the first word of a ROM header in each of the byte orders dumps come in
+const Z64_MAGIC: u32 = 0x8037_1240;
+const V64_MAGIC: u32 = 0x3780_4012;
+const N64_MAGIC: u32 = 0x4012_3780;
+
+// CRC32s of the retail IPL3s, the same ones `rom::CicType` recognises at runtime
+const KNOWN_IPL3S: &[(u32, u32)] = &[
+    (0x6170_A4A1, 6101),
+    (0x90BB_6CB5, 6102),
+    (0x0B05_0EE0, 6103),
+    (0x98BC_2C86, 6105),
+    (0xACC8_580A, 6106),
+    (0x009E_9EA3, 7102),
+    (0x0E01_8159, 8303),
+];
+
+// the 7xxx CICs are the PAL versions, which accept the same IPL3s
+const CIC_ALIASES: &[(u32, u32)] = &[(7101, 6102), (7103, 6103), (7105, 6105), (7106, 6106)];
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+
+    for &byte in data {
+        crc ^= byte as u32;
+
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+
+    !crc
+}
+
+/// where the IPL3 comes from: `N64_IPL3` names a raw IPL3 or a ROM to take it from, otherwise it's
+/// libdragon's, picked with `N64_IPL3_VARIANT` (`compat` by default)
+fn ipl3_file() -> PathBuf {
+    if let Some(path) = env::var_os("N64_IPL3") {
+        return path.into();
+    }
+
+    let Some(libdragon) = env::var_os("N64_INST") else {
+        panic!("Cannot find libdragon path (required for feature `ipl3' unless N64_IPL3 is set); ensure N64_INST is set correctly");
+    };
+
+    let variant = env::var("N64_IPL3_VARIANT").unwrap_or_else(|_| "compat".into());
+
+    Path::new(&libdragon).join(format!("boot/bin/ipl3_{variant}.z64"))
+}
+
+/// accepts a bare IPL3, or anything with a ROM header in front of one, in any byte order
+fn extract_ipl3(data: &[u8], path: &Path) -> Vec<u8> {
+    if data.len() < 0x1000 {
+        if data.len() != IPL3_SIZE {
+            panic!(
+                "IPL3 in {} is {:#X} bytes; it must be exactly {IPL3_SIZE:#X}, or a ROM containing one",
+                path.display(),
+                data.len()
+            );
+        }
+
+        return data.to_vec();
+    }
+
+    let mut rom = data[..0x1000].to_vec();
+
+    match u32::from_be_bytes(rom[..4].try_into().unwrap()) {
+        Z64_MAGIC => {}
+        V64_MAGIC => rom.chunks_exact_mut(2).for_each(|c| c.reverse()),
+        N64_MAGIC => rom.chunks_exact_mut(4).for_each(|c| c.reverse()),
+        magic => panic!(
+            "{} doesn't look like a ROM (first word {magic:#010X}); an IPL3 on its own must be exactly {IPL3_SIZE:#X} bytes",
+            path.display()
+        ),
+    }
+
+    rom[HEADER_SIZE..].to_vec()
+}
+
+/// warns if the IPL3 is one we know won't boot with the CIC in `N64_CIC` (6102 by default)
+fn check_cic(ipl3: &[u8], path: &Path) {
+    let explicit = env::var("N64_CIC").ok();
+
+    let target = match explicit.as_deref().map(str::parse::<u32>) {
+        None => 6102,
+        Some(Ok(cic)) => cic,
+        Some(Err(_)) => panic!("N64_CIC must be a CIC part number, e.g. 6102"),
+    };
+
+    let target = CIC_ALIASES
+        .iter()
+        .find(|&&(alias, _)| alias == target)
+        .map_or(target, |&(_, cic)| cic);
+
+    let crc = crc32(ipl3);
+
+    match KNOWN_IPL3S.iter().find(|&&(known, _)| known == crc) {
+        Some(&(_, cic)) if cic != target => println!(
+            "cargo:warning=IPL3 in {} is for CIC-{cic}, but the target is CIC-{target}; the console won't boot it",
+            path.display()
+        ),
+        Some(_) => {}
+        // libdragon's IPL3s aren't retail ones, so only mention it if a CIC was asked for
+        None if explicit.is_some() => println!(
+            "cargo:warning=IPL3 in {} (CRC32 {crc:08X}) isn't a known retail IPL3, so it can't be checked against CIC-{target}",
+            path.display()
+        ),
+        None => {}
+    }
+}
+
 fn main() {
-    if env::var_os("CARGO_FEATURE_IPL3").is_some() {
-        let Some(libdragon) = env::var_os("N64_INST") else {
-            panic!("Cannot find libdragon path (required for feature `ipl3'); ensure N64_INST is set correctly");
-        };
+    for var in ["N64_INST", "N64_IPL3", "N64_IPL3_VARIANT", "N64_CIC"] {
+        println!("cargo:rerun-if-env-changed={var}");
+    }
 
+    if env::var_os("CARGO_FEATURE_IPL3").is_some() {
         let out_dir = env::var_os("OUT_DIR").unwrap();
 
         let dest_path = Path::new(&out_dir).join("ipl3.rs");
 
-        let ipl3_file = Path::new(&libdragon).join("boot/bin/ipl3_compat.z64");
+        let ipl3_file = ipl3_file();
 
-        let Ok(ipl3) = read(&ipl3_file) else {
-            panic!("Failed to read IPL3 file from {}; ensure libdragon is checked out on the `preview' branch", ipl3_file.display());
+        let Ok(data) = read(&ipl3_file) else {
+            panic!("Failed to read IPL3 file from {}; ensure N64_IPL3 is correct, or that libdragon is checked out on the `preview' branch", ipl3_file.display());
         };
 
-        if let Err(e) = write(&dest_path, format!("{:?}", &ipl3[0x40..0x1000])) {
+        let ipl3 = extract_ipl3(&data, &ipl3_file);
+
+        check_cic(&ipl3, &ipl3_file);
+
+        if let Err(e) = write(&dest_path, format!("{:?}", ipl3)) {
             panic!(
                 "Failed to write IPL3 file to {}; error: {e}",
                 dest_path.display()
@@ -0,0 +1,50 @@
+//! reads the controllers in the background while the frame is drawn, using `Si::enable_async`
+#![no_std]
+#![no_main]
+
+use n64::si::si;
+use n64::text::Colour;
+use n64::vi::vi;
+
+#[no_mangle]
+fn main() -> ! {
+    let si = si();
+    let vi = vi();
+
+    si.enable_async();
+    vi.init_auto();
+
+    let mut frame = 0u32;
+
+    loop {
+        let handle = si
+            .start_read_controllers()
+            .expect("the previous read was collected, so the SI should be free");
+
+        // the PIF takes most of a millisecond to answer, which drawing can overlap with
+        vi.clear_framebuffer();
+        vi.print_string(2, 1, Colour::WHITE, "Async controller read");
+        vi.print_u32(2, 3, Colour::GREY, frame);
+
+        let controllers = loop {
+            if let Some(controllers) = si.poll_read_controllers(handle) {
+                break controllers;
+            }
+        };
+
+        for (port, controller) in controllers.iter().enumerate() {
+            let y = 5 + port;
+
+            match controller {
+                Ok(Some(data)) => vi.print_u16(2, y, Colour::GREEN, data.buttons()),
+                Ok(None) => vi.print_string(2, y, Colour::GREY, "----"),
+                Err(()) => vi.print_string(2, y, Colour::RED, "Error"),
+            }
+        }
+
+        vi.wait_vsync();
+        vi.next_framebuffer();
+
+        frame = frame.wrapping_add(1);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{crc16, FLAG_ACK, HEADER_SIZE, MAX_FRAME, MAX_PAYLOAD, SYNC};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    pub channel: u8,
+    pub seq: u8,
+    pub flags: u8,
+    len: u8,
+    payload: [u8; MAX_PAYLOAD],
+}
+
+impl Frame {
+    pub const EMPTY: Self = Self {
+        channel: 0,
+        seq: 0,
+        flags: 0,
+        len: 0,
+        payload: [0; MAX_PAYLOAD],
+    };
+
+    /// returns `None` if `payload` doesn't fit in a frame
+    pub fn data(channel: u8, seq: u8, payload: &[u8]) -> Option<Self> {
+        if payload.len() > MAX_PAYLOAD {
+            return None;
+        }
+
+        let mut frame = Self {
+            channel,
+            seq,
+            len: payload.len() as u8,
+            ..Self::EMPTY
+        };
+        frame.payload[..payload.len()].copy_from_slice(payload);
+
+        Some(frame)
+    }
+
+    pub fn ack(channel: u8, seq: u8) -> Self {
+        Self {
+            channel,
+            seq,
+            flags: FLAG_ACK,
+            ..Self::EMPTY
+        }
+    }
+
+    pub fn payload(&self) -> &[u8] {
+        &self.payload[..self.len as usize]
+    }
+
+    /// writes the frame to `buf`, returning how many bytes it took up
+    pub fn encode(&self, buf: &mut [u8; MAX_FRAME]) -> usize {
+        let len = self.len as usize;
+
+        buf[..SYNC.len()].copy_from_slice(&SYNC);
+        buf[SYNC.len()..HEADER_SIZE].copy_from_slice(&[
+            self.len,
+            self.channel,
+            self.seq,
+            self.flags,
+        ]);
+        buf[HEADER_SIZE..HEADER_SIZE + len].copy_from_slice(self.payload());
+
+        let crc = crc16(&buf[SYNC.len()..HEADER_SIZE + len]);
+        buf[HEADER_SIZE + len..HEADER_SIZE + len + 2].copy_from_slice(&crc.to_be_bytes());
+
+        HEADER_SIZE + len + 2
+    }
+}
+
+/// reassembles frames from a byte stream, throwing away anything that doesn't check out
+pub struct Decoder {
+    buf: [u8; MAX_FRAME],
+    len: usize,
+}
+
+impl Decoder {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; MAX_FRAME],
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, byte: u8) -> Option<Frame> {
+        self.buf[self.len] = byte;
+        self.len += 1;
+
+        loop {
+            match self.check() {
+                Check::Incomplete => return None,
+                Check::Complete(frame) => {
+                    self.len = 0;
+                    return Some(frame);
+                }
+                Check::Invalid => {
+                    // resynchronise by trying again from the next byte
+                    self.buf.copy_within(1..self.len, 0);
+                    self.len -= 1;
+                }
+            }
+        }
+    }
+
+    fn check(&self) -> Check {
+        let buf = &self.buf[..self.len];
+
+        // compare as much of the sync pattern as has arrived
+        let sync_len = buf.len().min(SYNC.len());
+        if buf[..sync_len] != SYNC[..sync_len] {
+            return Check::Invalid;
+        }
+
+        if buf.len() < HEADER_SIZE {
+            return Check::Incomplete;
+        }
+
+        let len = buf[SYNC.len()] as usize;
+        if len > MAX_PAYLOAD {
+            return Check::Invalid;
+        }
+
+        if buf.len() < HEADER_SIZE + len + 2 {
+            return Check::Incomplete;
+        }
+
+        let crc = u16::from_be_bytes([buf[HEADER_SIZE + len], buf[HEADER_SIZE + len + 1]]);
+        if crc != crc16(&buf[SYNC.len()..HEADER_SIZE + len]) {
+            return Check::Invalid;
+        }
+
+        let [_, channel, seq, flags] = buf[SYNC.len()..HEADER_SIZE].try_into().unwrap();
+
+        let mut frame = Frame {
+            channel,
+            seq,
+            flags,
+            len: len as u8,
+            payload: [0; MAX_PAYLOAD],
+        };
+        frame.payload[..len].copy_from_slice(&buf[HEADER_SIZE..HEADER_SIZE + len]);
+
+        Check::Complete(frame)
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+enum Check {
+    Incomplete,
+    Complete(Frame),
+    Invalid,
+}
@@ -0,0 +1,146 @@
+//! host-side transports, for talking to the console and for exercising the protocol without one
+
+use std::collections::VecDeque;
+use std::io::{ErrorKind, Read, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::Transport;
+
+/// deterministic damage to apply to bytes crossing a `Loopback`; every `n`th byte is affected
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Faults {
+    pub drop_every: Option<usize>,
+    pub corrupt_every: Option<usize>,
+}
+
+type Pipe = Arc<Mutex<VecDeque<u8>>>;
+
+/// one end of an in-memory link; the ends can be moved to separate threads
+pub struct Loopback {
+    tx: Pipe,
+    rx: Pipe,
+    faults: Faults,
+    sent: usize,
+    start: Instant,
+}
+
+impl Loopback {
+    pub fn pair(faults: Faults) -> (Self, Self) {
+        let a = Pipe::default();
+        let b = Pipe::default();
+        let start = Instant::now();
+
+        (
+            Self {
+                tx: a.clone(),
+                rx: b.clone(),
+                faults,
+                sent: 0,
+                start,
+            },
+            Self {
+                tx: b,
+                rx: a,
+                faults,
+                sent: 0,
+                start,
+            },
+        )
+    }
+}
+
+impl Transport for Loopback {
+    fn exchange(&mut self, tx: &[u8], rx: &mut [u8]) -> usize {
+        {
+            let mut pipe = self.tx.lock().unwrap();
+
+            for &byte in tx {
+                self.sent += 1;
+
+                let hit = |every: Option<usize>| every.is_some_and(|n| self.sent % n == 0);
+
+                if hit(self.faults.drop_every) {
+                    continue;
+                }
+
+                pipe.push_back(if hit(self.faults.corrupt_every) {
+                    byte ^ 0x10
+                } else {
+                    byte
+                });
+            }
+        }
+
+        let mut pipe = self.rx.lock().unwrap();
+        let len = rx.len().min(pipe.len());
+
+        for (dst, src) in rx.iter_mut().zip(pipe.drain(..len)) {
+            *dst = src;
+        }
+
+        len
+    }
+
+    fn now(&mut self) -> u32 {
+        self.start.elapsed().as_millis() as u32
+    }
+
+    fn ticks_per_ms(&self) -> u32 {
+        1
+    }
+}
+
+/// wraps anything byte-oriented, like a serial port or a socket to a probe bridge
+///
+/// reads should be set up to time out quickly, since `exchange` is expected not to block
+pub struct IoTransport<P: Read + Write> {
+    port: P,
+    start: Instant,
+}
+
+impl<P: Read + Write> IoTransport<P> {
+    pub fn new(port: P) -> Self {
+        Self {
+            port,
+            start: Instant::now(),
+        }
+    }
+
+    pub fn into_inner(self) -> P {
+        self.port
+    }
+}
+
+impl<P: Read + Write> Transport for IoTransport<P> {
+    fn exchange(&mut self, tx: &[u8], rx: &mut [u8]) -> usize {
+        // a broken port shows up as a timeout in the link, which is the best it can do
+        if !tx.is_empty()
+            && self
+                .port
+                .write_all(tx)
+                .and_then(|_| self.port.flush())
+                .is_err()
+        {
+            return 0;
+        }
+
+        if rx.is_empty() {
+            return 0;
+        }
+
+        match self.port.read(rx) {
+            Ok(len) => len,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => 0,
+            Err(_) => 0,
+        }
+    }
+
+    fn now(&mut self) -> u32 {
+        self.start.elapsed().as_millis() as u32
+    }
+
+    fn ticks_per_ms(&self) -> u32 {
+        1
+    }
+}
@@ -0,0 +1,207 @@
+//! a reliable, framed protocol for byte links which can drop or corrupt data, such as the iQue
+//! debug probe
+//!
+//! frames look like this, with the CRC covering everything after the sync bytes:
+//!
+//! | sync (2) | length (1) | channel (1) | sequence (1) | flags (1) | payload (length) | CRC-16 (2) |
+//!
+//! delivery is stop-and-wait: each data frame must be acknowledged before the next one is sent,
+//! and is retransmitted if the acknowledgement doesn't arrive in time
+
+#![no_std]
+
+#[cfg(feature = "std")]
+extern crate std;
+
+mod crc;
+mod frame;
+#[cfg(feature = "std")]
+pub mod host;
+
+pub use crc::crc16;
+pub use frame::{Decoder, Frame};
+
+pub const SYNC: [u8; 2] = [0xA5, 0x5A];
+pub const MAX_PAYLOAD: usize = 64;
+pub const HEADER_SIZE: usize = SYNC.len() + 4;
+pub const MAX_FRAME: usize = HEADER_SIZE + MAX_PAYLOAD + 2;
+
+pub const FLAG_ACK: u8 = 1 << 0;
+
+const RX_QUEUE_LEN: usize = 4;
+
+pub trait Transport {
+    /// sends `tx`, and fills `rx` with whatever arrived meanwhile, returning how many bytes that was
+    fn exchange(&mut self, tx: &[u8], rx: &mut [u8]) -> usize;
+
+    /// a free-running counter, which is allowed to wrap
+    fn now(&mut self) -> u32;
+
+    fn ticks_per_ms(&self) -> u32;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkError {
+    PayloadTooLarge,
+    /// the other end never acknowledged the frame
+    Timeout,
+}
+
+pub struct Link<T: Transport> {
+    transport: T,
+    decoder: Decoder,
+    tx_seq: u8,
+    last_rx_seq: Option<u8>,
+    rx_queue: [Frame; RX_QUEUE_LEN],
+    rx_head: usize,
+    rx_len: usize,
+    timeout_ms: u32,
+    max_retries: u32,
+}
+
+impl<T: Transport> Link<T> {
+    pub fn new(transport: T) -> Self {
+        Self {
+            transport,
+            decoder: Decoder::new(),
+            tx_seq: 0,
+            last_rx_seq: None,
+            rx_queue: [Frame::EMPTY; RX_QUEUE_LEN],
+            rx_head: 0,
+            rx_len: 0,
+            timeout_ms: 100,
+            max_retries: 10,
+        }
+    }
+
+    pub fn set_timeout(&mut self, timeout_ms: u32, max_retries: u32) {
+        self.timeout_ms = timeout_ms;
+        self.max_retries = max_retries;
+    }
+
+    pub fn transport(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    fn transmit(&mut self, frame: &Frame) -> Option<u8> {
+        let mut buf = [0; MAX_FRAME];
+        let len = frame.encode(&mut buf);
+
+        let mut rx = [0; MAX_FRAME];
+        let received = self.transport.exchange(&buf[..len], &mut rx);
+
+        self.receive(&rx[..received])
+    }
+
+    /// feeds received bytes through the decoder, queueing and acknowledging data, and returns the
+    /// sequence number of any acknowledgement that came in
+    fn receive(&mut self, bytes: &[u8]) -> Option<u8> {
+        let mut acked = None;
+
+        for &byte in bytes {
+            if let Some(seq) = self.receive_byte(byte) {
+                acked = Some(seq);
+            }
+        }
+
+        acked
+    }
+
+    fn receive_byte(&mut self, byte: u8) -> Option<u8> {
+        let frame = self.decoder.push(byte)?;
+
+        if frame.flags & FLAG_ACK != 0 {
+            return Some(frame.seq);
+        }
+
+        // the ack can be lost too, in which case the other end resends the frame; it still needs
+        // acknowledging, but mustn't be delivered twice
+        if self.last_rx_seq != Some(frame.seq) {
+            if self.rx_len == RX_QUEUE_LEN {
+                // no room, so don't ack it and let it be resent later
+                return None;
+            }
+
+            self.last_rx_seq = Some(frame.seq);
+
+            self.rx_queue[(self.rx_head + self.rx_len) % RX_QUEUE_LEN] = frame;
+            self.rx_len += 1;
+        }
+
+        // the other end only has one frame in flight at a time, so this can't recurse far
+        self.transmit(&Frame::ack(frame.channel, frame.seq))
+    }
+
+    /// sends `payload` on `channel`, blocking until the other end acknowledges it
+    pub fn send(&mut self, channel: u8, payload: &[u8]) -> Result<(), LinkError> {
+        let frame = Frame::data(channel, self.tx_seq, payload).ok_or(LinkError::PayloadTooLarge)?;
+
+        let timeout = self
+            .timeout_ms
+            .saturating_mul(self.transport.ticks_per_ms());
+
+        for _ in 0..=self.max_retries {
+            let start = self.transport.now();
+
+            if self.transmit(&frame) == Some(frame.seq) {
+                self.tx_seq = self.tx_seq.wrapping_add(1);
+                return Ok(());
+            }
+
+            while self.transport.now().wrapping_sub(start) < timeout {
+                if self.poll_queue() == Some(frame.seq) {
+                    self.tx_seq = self.tx_seq.wrapping_add(1);
+                    return Ok(());
+                }
+            }
+        }
+
+        Err(LinkError::Timeout)
+    }
+
+    /// checks for incoming data, returning the oldest frame that hasn't been collected yet
+    pub fn poll(&mut self) -> Option<Frame> {
+        if self.rx_len == 0 {
+            self.poll_queue();
+        }
+
+        if self.rx_len == 0 {
+            return None;
+        }
+
+        let frame = self.rx_queue[self.rx_head];
+        self.rx_head = (self.rx_head + 1) % RX_QUEUE_LEN;
+        self.rx_len -= 1;
+
+        Some(frame)
+    }
+
+    /// like `poll`, but only returns frames for `channel`, leaving others queued
+    pub fn poll_channel(&mut self, channel: u8) -> Option<Frame> {
+        self.poll_queue();
+
+        let index = (0..self.rx_len)
+            .map(|i| (self.rx_head + i) % RX_QUEUE_LEN)
+            .find(|&i| self.rx_queue[i].channel == channel)?;
+
+        let frame = self.rx_queue[index];
+
+        // close the gap, keeping the rest in order
+        let mut i = index;
+        while i != (self.rx_head + self.rx_len - 1) % RX_QUEUE_LEN {
+            let next = (i + 1) % RX_QUEUE_LEN;
+            self.rx_queue[i] = self.rx_queue[next];
+            i = next;
+        }
+        self.rx_len -= 1;
+
+        Some(frame)
+    }
+
+    fn poll_queue(&mut self) -> Option<u8> {
+        let mut rx = [0; MAX_FRAME];
+        let received = self.transport.exchange(&[], &mut rx);
+
+        self.receive(&rx[..received])
+    }
+}
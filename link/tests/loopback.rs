@@ -0,0 +1,61 @@
+use std::thread;
+
+use n64_link::host::{Faults, Loopback};
+use n64_link::Link;
+
+fn exchange(faults: Faults) {
+    let (a, b) = Loopback::pair(faults);
+
+    let peer = thread::spawn(move || {
+        let mut link = Link::new(b);
+        let mut received = Vec::new();
+
+        while received.len() < 16 {
+            if let Some(frame) = link.poll() {
+                received.push((frame.channel, frame.payload().to_vec()));
+            }
+        }
+
+        // keep answering retransmissions until the other side is done
+        for _ in 0..10_000 {
+            link.poll();
+        }
+
+        received
+    });
+
+    let mut link = Link::new(a);
+    link.set_timeout(5, 100);
+
+    for i in 0..16u8 {
+        link.send(i % 3, &[i; 40]).unwrap();
+    }
+
+    let received = peer.join().unwrap();
+
+    for (i, (channel, payload)) in received.into_iter().enumerate() {
+        assert_eq!(channel, i as u8 % 3);
+        assert_eq!(payload, vec![i as u8; 40]);
+    }
+}
+
+#[test]
+fn clean() {
+    exchange(Faults::default());
+}
+
+#[test]
+fn dropped_bytes() {
+    exchange(Faults {
+        drop_every: Some(97),
+        corrupt_every: None,
+    });
+}
+
+#[test]
+fn corrupted_bytes() {
+    exchange(Faults {
+        drop_every: None,
+        corrupt_every: Some(89),
+    });
+}
@@ -0,0 +1,55 @@
+use crate::Cic;
+
+/// the IPL3 checksums this much of the ROM, starting straight after itself
+pub const CHECKSUM_START: usize = 0x1000;
+pub const CHECKSUM_LEN: usize = 0x10_0000;
+
+impl Cic {
+    fn seed(self) -> u32 {
+        match self {
+            Self::Cic6102 => 0xF8CA_4DDC,
+            Self::Cic6105 => 0xDF26_F436,
+        }
+    }
+}
+
+fn word(rom: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(rom[offset..offset + 4].try_into().unwrap())
+}
+
+/// the two words the IPL3 compares against offsets 0x10 and 0x14 of the header before booting;
+/// `rom` has to be at least `CHECKSUM_START + CHECKSUM_LEN` bytes
+pub fn checksum(rom: &[u8], cic: Cic) -> (u32, u32) {
+    let seed = cic.seed();
+
+    let (mut t1, mut t2, mut t3, mut t4, mut t5, mut t6) = (seed, seed, seed, seed, seed, seed);
+
+    for i in (CHECKSUM_START..CHECKSUM_START + CHECKSUM_LEN).step_by(4) {
+        let d = word(rom, i);
+
+        let (sum, carry) = t6.overflowing_add(d);
+        if carry {
+            t4 = t4.wrapping_add(1);
+        }
+        t6 = sum;
+
+        t3 ^= d;
+
+        let r = d.rotate_left(d & 0x1F);
+        t5 = t5.wrapping_add(r);
+
+        if t2 > d {
+            t2 ^= r;
+        } else {
+            t2 ^= t6 ^ d;
+        }
+
+        t1 = t1.wrapping_add(match cic {
+            // mixes in part of its own IPL3
+            Cic::Cic6105 => word(rom, 0x0750 + (i & 0xFF)) ^ d,
+            Cic::Cic6102 => t5 ^ d,
+        });
+    }
+
+    (t6 ^ t4 ^ t3, t5 ^ t2 ^ t1)
+}
@@ -0,0 +1,260 @@
+//! lays out a linked ELF as a bootable big-endian (.z64) ROM
+
+use std::fmt;
+
+use object::elf::{PT_LOAD, SHF_ALLOC, SHT_PROGBITS};
+use object::read::elf::{ElfFile32, FileHeader, ProgramHeader, SectionHeader};
+use object::Endianness;
+
+mod checksum;
+
+pub use checksum::{checksum, CHECKSUM_LEN, CHECKSUM_START};
+
+pub const HEADER_SIZE: usize = 0x40;
+pub const IPL3_SIZE: usize = 0x1000 - HEADER_SIZE;
+
+/// where the IPL3 copies the first megabyte of code from
+pub const BOOT_OFFSET: usize = 0x1000;
+
+/// the cartridge's physical address range, for sections the linker gave ROM load addresses
+const CART_BASE: u32 = 0x1000_0000;
+const CART_END: u32 = 0x1FC0_0000;
+
+pub const TITLE_LEN: usize = 20;
+
+// ROMs are padded to a whole number of these
+const SIZE_ALIGN: usize = 0x10_0000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cic {
+    Cic6102,
+    Cic6105,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    pub cic: Cic,
+    pub title: [u8; TITLE_LEN],
+    pub game_code: [u8; 4],
+    pub version: u8,
+    /// the first word of the header, which the boot code sets domain 1 from
+    pub pi_config: u32,
+    pub clock_rate: u32,
+    pub release: u32,
+    /// replaces the ELF's `.ipl3`, which is empty unless it was built with the `ipl3` feature
+    pub ipl3: Option<Vec<u8>>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            cic: Cic::Cic6102,
+            title: [b' '; TITLE_LEN],
+            game_code: *b"N\0\0E",
+            version: 0,
+            pi_config: 0x8037_1240,
+            clock_rate: 0x0000_000F,
+            release: 0x0000_144C,
+            ipl3: None,
+        }
+    }
+}
+
+impl Options {
+    /// pads with spaces, or truncates
+    pub fn set_title(&mut self, title: &str) {
+        self.title = [b' '; TITLE_LEN];
+
+        for (dst, &src) in self.title.iter_mut().zip(title.as_bytes()) {
+            *dst = src;
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RomError {
+    Elf(object::Error),
+    NotBigEndian,
+    MissingSection(&'static str),
+    /// the ELF has no IPL3 and none was given
+    Ipl3Missing,
+    /// the IPL3 wasn't exactly `IPL3_SIZE` bytes
+    Ipl3Size(usize),
+    /// a section would have to go somewhere other than after the header and IPL3
+    Misplaced(String),
+    Overlap(String, String),
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Elf(e) => write!(f, "couldn't parse ELF: {e}"),
+            Self::NotBigEndian => write!(f, "ELF must be big-endian"),
+            Self::MissingSection(name) => write!(f, "ELF has no `{name}' section"),
+            Self::Ipl3Missing => write!(
+                f,
+                "ELF has no IPL3; build with the `ipl3' feature or supply one"
+            ),
+            Self::Ipl3Size(len) => write!(
+                f,
+                "IPL3 is {len:#X} bytes, but must be exactly {IPL3_SIZE:#X}"
+            ),
+            Self::Misplaced(name) => write!(f, "section `{name}' doesn't fit in the ROM layout"),
+            Self::Overlap(a, b) => write!(f, "sections `{a}' and `{b}' overlap in the ROM"),
+        }
+    }
+}
+
+impl std::error::Error for RomError {}
+
+impl From<object::Error> for RomError {
+    fn from(e: object::Error) -> Self {
+        Self::Elf(e)
+    }
+}
+
+struct Placed<'a> {
+    name: String,
+    offset: usize,
+    data: &'a [u8],
+}
+
+pub fn build_rom(elf: &[u8], options: &Options) -> Result<Vec<u8>, RomError> {
+    let file = ElfFile32::<Endianness>::parse(elf)?;
+    let endian = file.endian();
+
+    if endian != Endianness::Big {
+        return Err(RomError::NotBigEndian);
+    }
+
+    let header = file.elf_header();
+    let sections = header.sections(endian, elf)?;
+    let segments = header.program_headers(endian, elf)?;
+
+    // sections with a load address in cartridge space go exactly there; the rest are where the
+    // IPL3 copies them, relative to `.entry`
+    let lma = |offset: u32, addr: u32| {
+        segments
+            .iter()
+            .filter(|p| p.p_type(endian) == PT_LOAD)
+            .find(|p| {
+                let start = p.p_offset(endian);
+                (start..start + p.p_filesz(endian)).contains(&offset)
+            })
+            .map_or(addr, |p| p.p_paddr(endian) + (offset - p.p_offset(endian)))
+    };
+
+    let mut entry = None;
+    let mut ipl3 = None;
+    let mut placed = Vec::new();
+
+    for section in sections.iter() {
+        let name = String::from_utf8_lossy(sections.section_name(endian, section)?).into_owned();
+
+        if name == ".entry" {
+            entry = Some(section.sh_addr(endian));
+        }
+
+        if name == ".ipl3" {
+            ipl3 = Some(section.data(endian, elf)?);
+            continue;
+        }
+
+        if section.sh_type(endian) != SHT_PROGBITS
+            || section.sh_flags(endian) & SHF_ALLOC == 0
+            || section.sh_size(endian) == 0
+        {
+            continue;
+        }
+
+        placed.push((
+            name,
+            lma(section.sh_offset(endian), section.sh_addr(endian)),
+            section.sh_addr(endian),
+            section.data(endian, elf)?,
+        ));
+    }
+
+    let entry = entry.ok_or(RomError::MissingSection(".entry"))?;
+
+    // without the `ipl3` feature the section is left uninitialised, so it's either empty or zeroed
+    let ipl3 = match (&options.ipl3, ipl3) {
+        (Some(data), _) => data.as_slice(),
+        (None, Some(data)) if data.iter().any(|&b| b != 0) => data,
+        _ => return Err(RomError::Ipl3Missing),
+    };
+
+    if ipl3.len() != IPL3_SIZE {
+        return Err(RomError::Ipl3Size(ipl3.len()));
+    }
+
+    let mut layout = placed
+        .into_iter()
+        .map(|(name, lma, vma, data)| {
+            let offset = if (CART_BASE..CART_END).contains(&lma) {
+                (lma - CART_BASE) as usize
+            } else if vma >= entry {
+                BOOT_OFFSET + (vma - entry) as usize
+            } else {
+                return Err(RomError::Misplaced(name));
+            };
+
+            if offset < BOOT_OFFSET {
+                return Err(RomError::Misplaced(name));
+            }
+
+            Ok(Placed { name, offset, data })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    layout.sort_by_key(|p| p.offset);
+
+    for pair in layout.windows(2) {
+        if pair[0].offset + pair[0].data.len() > pair[1].offset {
+            return Err(RomError::Overlap(
+                pair[0].name.clone(),
+                pair[1].name.clone(),
+            ));
+        }
+    }
+
+    let end = layout
+        .iter()
+        .map(|p| p.offset + p.data.len())
+        .max()
+        .unwrap_or(BOOT_OFFSET)
+        .max(CHECKSUM_START + CHECKSUM_LEN);
+
+    let mut rom = vec![0; end.next_multiple_of(SIZE_ALIGN)];
+
+    rom[HEADER_SIZE..BOOT_OFFSET].copy_from_slice(ipl3);
+
+    for p in &layout {
+        rom[p.offset..p.offset + p.data.len()].copy_from_slice(p.data);
+    }
+
+    write_header(&mut rom, entry, options);
+
+    Ok(rom)
+}
+
+fn write_header(rom: &mut [u8], entry: u32, options: &Options) {
+    let mut put = |offset: usize, val: u32| {
+        rom[offset..offset + 4].copy_from_slice(&val.to_be_bytes());
+    };
+
+    put(0x00, options.pi_config);
+    put(0x04, options.clock_rate);
+    put(0x08, entry);
+    put(0x0C, options.release);
+
+    rom[0x20..0x20 + TITLE_LEN].copy_from_slice(&options.title);
+    rom[0x3B..0x3F].copy_from_slice(&options.game_code);
+    rom[0x3F] = options.version;
+
+    // the checksum doesn't cover the header, so it can go in last
+    let (crc1, crc2) = checksum(rom, options.cic);
+
+    rom[0x10..0x14].copy_from_slice(&crc1.to_be_bytes());
+    rom[0x14..0x18].copy_from_slice(&crc2.to_be_bytes());
+}
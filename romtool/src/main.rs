@@ -0,0 +1,83 @@
+use std::env;
+use std::fs;
+use std::process::exit;
+
+use n64_romtool::{build_rom, Cic, Options, BOOT_OFFSET, HEADER_SIZE, IPL3_SIZE};
+
+const USAGE: &str = "usage: n64-romtool [options] <elf> <rom>
+
+options:
+    --title <title>     up to 20 characters for the header
+    --code <code>       four-character game code, e.g. NSME
+    --version <n>       ROM revision
+    --cic <6102|6105>   which CIC the IPL3 is for (default 6102)
+    --ipl3 <file>       IPL3 to use instead of the ELF's; either 0xFC0 bytes or a whole ROM";
+
+fn fail(msg: &str) -> ! {
+    eprintln!("n64-romtool: {msg}");
+    exit(1)
+}
+
+fn main() {
+    let mut options = Options::default();
+    let mut paths = Vec::new();
+
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .unwrap_or_else(|| fail(&format!("`{arg}' needs a value")))
+        };
+
+        match arg.as_str() {
+            "--title" => options.set_title(&value()),
+            "--code" => {
+                options.game_code = value()
+                    .as_bytes()
+                    .try_into()
+                    .unwrap_or_else(|_| fail("game code must be four characters"))
+            }
+            "--version" => {
+                options.version = value()
+                    .parse()
+                    .unwrap_or_else(|_| fail("version must be 0-255"))
+            }
+            "--cic" => {
+                options.cic = match value().as_str() {
+                    "6102" => Cic::Cic6102,
+                    "6105" => Cic::Cic6105,
+                    cic => fail(&format!("unsupported CIC `{cic}'")),
+                }
+            }
+            "--ipl3" => {
+                let path = value();
+                let data =
+                    fs::read(&path).unwrap_or_else(|e| fail(&format!("couldn't read {path}: {e}")));
+
+                // accept a whole ROM too, and take the IPL3 from it
+                options.ipl3 = Some(match data.len() {
+                    IPL3_SIZE => data,
+                    n if n >= BOOT_OFFSET => data[HEADER_SIZE..BOOT_OFFSET].to_vec(),
+                    _ => fail(&format!("{path} is too small to hold an IPL3")),
+                });
+            }
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return;
+            }
+            _ if arg.starts_with("--") => fail(&format!("unknown option `{arg}'\n\n{USAGE}")),
+            _ => paths.push(arg),
+        }
+    }
+
+    let [elf, rom] = paths.as_slice() else {
+        fail(USAGE)
+    };
+
+    let data = fs::read(elf).unwrap_or_else(|e| fail(&format!("couldn't read {elf}: {e}")));
+
+    let out = build_rom(&data, &options).unwrap_or_else(|e| fail(&e.to_string()));
+
+    fs::write(rom, out).unwrap_or_else(|e| fail(&format!("couldn't write {rom}: {e}")));
+}
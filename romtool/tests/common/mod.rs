@@ -0,0 +1,171 @@
+//! writes the minimal big-endian MIPS ELF32 executables the tests lay out
+
+#![allow(dead_code)]
+
+pub const SHT_PROGBITS: u32 = 1;
+pub const SHT_NOBITS: u32 = 8;
+
+const SHF_WRITE: u32 = 1;
+const SHF_ALLOC: u32 = 2;
+
+const EHDR_SIZE: usize = 52;
+const PHDR_SIZE: usize = 32;
+const SHDR_SIZE: usize = 40;
+
+pub struct Section {
+    pub name: &'static str,
+    pub kind: u32,
+    pub vma: u32,
+    pub lma: u32,
+    pub data: Vec<u8>,
+}
+
+impl Section {
+    /// loaded where it runs
+    pub fn new(name: &'static str, vma: u32, data: Vec<u8>) -> Self {
+        Self {
+            name,
+            kind: SHT_PROGBITS,
+            vma,
+            lma: vma,
+            data,
+        }
+    }
+
+    pub fn at(mut self, lma: u32) -> Self {
+        self.lma = lma;
+        self
+    }
+
+    pub fn nobits(mut self) -> Self {
+        self.kind = SHT_NOBITS;
+        self
+    }
+}
+
+/// deterministic, non-zero filler
+pub fn pattern(seed: u32, len: usize) -> Vec<u8> {
+    (0..len as u32)
+        .map(|i| (i.wrapping_mul(31) ^ seed.wrapping_mul(0x9E37)) as u8 | 1)
+        .collect()
+}
+
+fn put(out: &mut Vec<u8>, words: &[u32]) {
+    for w in words {
+        out.extend_from_slice(&w.to_be_bytes());
+    }
+}
+
+/// each section gets a `PT_LOAD` of its own, with its load address as `p_paddr` like `ld` emits
+pub fn elf(entry: u32, sections: &[Section]) -> Vec<u8> {
+    let mut shstrtab = vec![0];
+    let mut names = Vec::new();
+
+    for s in sections.iter().map(|s| s.name).chain([".shstrtab"]) {
+        names.push(shstrtab.len() as u32);
+        shstrtab.extend_from_slice(s.as_bytes());
+        shstrtab.push(0);
+    }
+
+    let phoff = EHDR_SIZE;
+    let mut offset = phoff + PHDR_SIZE * sections.len();
+
+    let mut offsets = Vec::new();
+    for s in sections {
+        offset = offset.next_multiple_of(16);
+        offsets.push(offset);
+
+        if s.kind == SHT_PROGBITS {
+            offset += s.data.len();
+        }
+    }
+
+    let shstrtab_offset = offset;
+    let shoff = (shstrtab_offset + shstrtab.len()).next_multiple_of(4);
+
+    let mut out = vec![0x7F, b'E', b'L', b'F', 1, 2, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    out.extend_from_slice(&2u16.to_be_bytes());
+    out.extend_from_slice(&8u16.to_be_bytes());
+    put(&mut out, &[1, entry, phoff as u32, shoff as u32, 0]);
+    for half in [
+        EHDR_SIZE,
+        PHDR_SIZE,
+        sections.len(),
+        SHDR_SIZE,
+        sections.len() + 2,
+        sections.len() + 1,
+    ] {
+        out.extend_from_slice(&(half as u16).to_be_bytes());
+    }
+
+    for (s, &offset) in sections.iter().zip(&offsets) {
+        let filesz = if s.kind == SHT_PROGBITS {
+            s.data.len() as u32
+        } else {
+            0
+        };
+
+        put(
+            &mut out,
+            &[
+                1,
+                offset as u32,
+                s.vma,
+                s.lma,
+                filesz,
+                s.data.len() as u32,
+                7,
+                16,
+            ],
+        );
+    }
+
+    for (s, &offset) in sections.iter().zip(&offsets) {
+        out.resize(offset, 0);
+        if s.kind == SHT_PROGBITS {
+            out.extend_from_slice(&s.data);
+        }
+    }
+
+    out.resize(shstrtab_offset, 0);
+    out.extend_from_slice(&shstrtab);
+    out.resize(shoff, 0);
+
+    put(&mut out, &[0; 10]);
+
+    for ((s, &offset), &name) in sections.iter().zip(&offsets).zip(&names) {
+        put(
+            &mut out,
+            &[
+                name,
+                s.kind,
+                SHF_ALLOC | SHF_WRITE,
+                s.vma,
+                offset as u32,
+                s.data.len() as u32,
+                0,
+                0,
+                16,
+                0,
+            ],
+        );
+    }
+
+    put(
+        &mut out,
+        &[
+            *names.last().unwrap(),
+            3,
+            0,
+            0,
+            shstrtab_offset as u32,
+            shstrtab.len() as u32,
+            0,
+            0,
+            1,
+            0,
+        ],
+    );
+
+    out
+}
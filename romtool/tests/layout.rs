@@ -0,0 +1,212 @@
+mod common;
+
+use std::process::Command;
+
+use common::{elf, pattern, Section};
+use n64_romtool::{
+    build_rom, checksum, Cic, Options, RomError, BOOT_OFFSET, CHECKSUM_LEN, CHECKSUM_START,
+    HEADER_SIZE, IPL3_SIZE,
+};
+
+const ENTRY: u32 = 0x8000_0400;
+
+fn word(rom: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(rom[offset..offset + 4].try_into().unwrap())
+}
+
+/// laid out like the linker script does it
+fn program(ipl3: Vec<u8>) -> Vec<Section> {
+    vec![
+        Section::new(".ipl3", 0x1000_0040, ipl3),
+        Section::new(".entry", ENTRY, pattern(1, 0x40)),
+        Section::new(".boot", ENTRY + 0x40, pattern(2, 0x200)),
+        Section::new(".text", ENTRY + 0x240, pattern(3, 0x1C0)),
+        // copied to the exception vectors by `setup_ints`, from where it was put in the ROM
+        Section::new(".int_handler", 0x8000_0000, pattern(4, 0x30)).at(0x1001_0000),
+        Section::new(".bss", ENTRY + 0x400, vec![0; 0x800]).nobits(),
+    ]
+}
+
+#[test]
+fn places_sections() {
+    let rom = build_rom(
+        &elf(ENTRY, &program(pattern(0, IPL3_SIZE))),
+        &Options::default(),
+    )
+    .unwrap();
+
+    assert_eq!(rom[HEADER_SIZE..BOOT_OFFSET], pattern(0, IPL3_SIZE));
+    assert_eq!(rom[0x1000..0x1040], pattern(1, 0x40));
+    assert_eq!(rom[0x1040..0x1240], pattern(2, 0x200));
+    assert_eq!(rom[0x1240..0x1400], pattern(3, 0x1C0));
+    assert_eq!(rom[0x1_0000..0x1_0030], pattern(4, 0x30));
+
+    // nothing in between, and no .bss
+    assert!(rom[0x1400..0x1_0000].iter().all(|&b| b == 0));
+    assert!(rom[0x1_0030..].iter().all(|&b| b == 0));
+}
+
+#[test]
+fn writes_header() {
+    let mut options = Options {
+        game_code: *b"NRSE",
+        version: 2,
+        ..Options::default()
+    };
+    options.set_title("RUST TEST");
+
+    let rom = build_rom(&elf(ENTRY, &program(pattern(0, IPL3_SIZE))), &options).unwrap();
+
+    assert_eq!(word(&rom, 0x00), 0x8037_1240);
+    assert_eq!(word(&rom, 0x04), 0x0000_000F);
+    assert_eq!(word(&rom, 0x08), ENTRY);
+    assert_eq!(word(&rom, 0x0C), 0x0000_144C);
+    assert_eq!(&rom[0x20..0x34], b"RUST TEST           ");
+    assert_eq!(&rom[0x3B..0x3F], b"NRSE");
+    assert_eq!(rom[0x3F], 2);
+
+    let (crc1, crc2) = checksum(&rom, Cic::Cic6102);
+    assert_eq!((word(&rom, 0x10), word(&rom, 0x14)), (crc1, crc2));
+}
+
+#[test]
+fn pads_to_whole_megabytes() {
+    let rom = build_rom(
+        &elf(ENTRY, &program(pattern(0, IPL3_SIZE))),
+        &Options::default(),
+    )
+    .unwrap();
+
+    // the checksummed megabyte starts after the IPL3, so it always spills into a second one
+    assert_eq!(rom.len(), 0x20_0000);
+
+    let mut big = program(pattern(0, IPL3_SIZE));
+    big.push(Section::new(".data", 0x8040_0000, pattern(5, 0x10)).at(0x1030_0000));
+
+    let rom = build_rom(&elf(ENTRY, &big), &Options::default()).unwrap();
+
+    assert_eq!(rom.len(), 0x40_0000);
+    assert_eq!(rom[0x30_0000..0x30_0010], pattern(5, 0x10));
+}
+
+#[test]
+fn checksum_of_blank_rom() {
+    let rom = vec![0; CHECKSUM_START + CHECKSUM_LEN];
+
+    // with every word zero, only the accumulator fed by `t5` moves, gaining the seed each word
+    let words = (CHECKSUM_LEN / 4) as u32;
+
+    for (cic, seed) in [(Cic::Cic6102, 0xF8CA_4DDCu32), (Cic::Cic6105, 0xDF26_F436)] {
+        let t1 = seed.wrapping_add(seed.wrapping_mul(words));
+        let expected = match cic {
+            Cic::Cic6102 => t1,
+            // the IPL3 it mixes in is blank too, so nothing is added
+            Cic::Cic6105 => seed,
+        };
+
+        assert_eq!(checksum(&rom, cic), (seed, expected));
+    }
+}
+
+#[test]
+fn cic_6105_mixes_in_ipl3() {
+    let rom = build_rom(
+        &elf(ENTRY, &program(pattern(0, IPL3_SIZE))),
+        &Options::default(),
+    )
+    .unwrap();
+
+    let mut other = rom.clone();
+    other[0x0750] ^= 0xFF;
+
+    assert_eq!(checksum(&rom, Cic::Cic6102), checksum(&other, Cic::Cic6102));
+    assert_ne!(checksum(&rom, Cic::Cic6105), checksum(&other, Cic::Cic6105));
+}
+
+#[test]
+fn ipl3_from_options() {
+    let program = elf(ENTRY, &program(vec![0; IPL3_SIZE]));
+
+    assert!(matches!(
+        build_rom(&program, &Options::default()),
+        Err(RomError::Ipl3Missing)
+    ));
+
+    let options = Options {
+        ipl3: Some(pattern(9, IPL3_SIZE)),
+        ..Options::default()
+    };
+
+    let rom = build_rom(&program, &options).unwrap();
+    assert_eq!(rom[HEADER_SIZE..BOOT_OFFSET], pattern(9, IPL3_SIZE));
+
+    let options = Options {
+        ipl3: Some(pattern(9, 0x100)),
+        ..Options::default()
+    };
+
+    assert!(matches!(
+        build_rom(&program, &options),
+        Err(RomError::Ipl3Size(0x100))
+    ));
+}
+
+#[test]
+fn rejects_bad_layouts() {
+    let mut overlapping = program(pattern(0, IPL3_SIZE));
+    overlapping.push(Section::new(".oops", 0x8000_0100, pattern(6, 0x10)).at(0x1000_1010));
+
+    assert!(matches!(
+        build_rom(&elf(ENTRY, &overlapping), &Options::default()),
+        Err(RomError::Overlap(..))
+    ));
+
+    let mut over_ipl3 = program(pattern(0, IPL3_SIZE));
+    over_ipl3.push(Section::new(".oops", 0x8000_0100, pattern(6, 0x10)).at(0x1000_0800));
+
+    assert!(matches!(
+        build_rom(&elf(ENTRY, &over_ipl3), &Options::default()),
+        Err(RomError::Misplaced(_))
+    ));
+
+    let no_entry: Vec<_> = program(pattern(0, IPL3_SIZE))
+        .into_iter()
+        .filter(|s| s.name != ".entry")
+        .collect();
+
+    assert!(matches!(
+        build_rom(&elf(ENTRY, &no_entry), &Options::default()),
+        Err(RomError::MissingSection(".entry"))
+    ));
+}
+
+#[test]
+fn command_line() {
+    let dir = std::env::temp_dir().join(format!("n64-romtool-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let elf_path = dir.join("program.elf");
+    let rom_path = dir.join("program.z64");
+
+    std::fs::write(&elf_path, elf(ENTRY, &program(pattern(0, IPL3_SIZE)))).unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_n64-romtool"))
+        .args(["--title", "CLI", "--code", "NCLE", "--cic", "6105"])
+        .arg(&elf_path)
+        .arg(&rom_path)
+        .status()
+        .unwrap();
+
+    assert!(status.success());
+
+    let rom = std::fs::read(&rom_path).unwrap();
+
+    assert_eq!(&rom[0x20..0x23], b"CLI");
+    assert_eq!(&rom[0x3B..0x3F], b"NCLE");
+    assert_eq!(
+        (word(&rom, 0x10), word(&rom, 0x14)),
+        checksum(&rom, Cic::Cic6105)
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,210 @@
+use core::ops::Range;
+use core::ptr::with_exposed_provenance_mut;
+use core::slice::from_raw_parts_mut;
+
+use crate::util::{phys_to_k0_usize, phys_to_k1_usize};
+use crate::{data_cache_writeback_raw, io_ptr};
+
+// where libultra on the iQue expects the launcher to have left these, just after osAppNMIBuffer
+const BB_EEPROM_ADDRESS: *mut u32 = io_ptr!(mut 0x0000_035C);
+const BB_EEPROM_SIZE: *mut u32 = io_ptr!(mut 0x0000_0360);
+const BB_FLASH_ADDRESS: *mut u32 = io_ptr!(mut 0x0000_0364);
+const BB_FLASH_SIZE: *mut u32 = io_ptr!(mut 0x0000_0368);
+const BB_SRAM_ADDRESS: *mut u32 = io_ptr!(mut 0x0000_036C);
+const BB_SRAM_SIZE: *mut u32 = io_ptr!(mut 0x0000_0370);
+const BB_PAK_ADDRESS: *mut [u32] = io_ptr!(mut 0x0000_0374; PAKS);
+const BB_PAK_SIZE: *mut u32 = io_ptr!(mut 0x0000_0384);
+const BB_STATE_DIRTY: *mut u32 = io_ptr!(mut 0x0000_03B4);
+
+pub const PAKS: usize = 4;
+
+// keeps each save on its own cache lines
+const SAVE_ALIGN: u32 = 0x10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveKind {
+    Eeprom,
+    Flash,
+    Sram,
+    Pak(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveError {
+    /// the saves don't fit in the region they were given
+    NoSpace,
+    Storage,
+}
+
+/// how much of each save type an app uses, from its content description; zero means none
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SaveLayout {
+    pub eeprom_size: u32,
+    pub flash_size: u32,
+    pub sram_size: u32,
+    pub pak_size: u32,
+    pub paks: usize,
+}
+
+/// where the launcher keeps an app's saves between sessions, usually files on the card
+///
+/// there's no card-backed implementation here yet, since `card` can only read pages; the launcher
+/// has to bring its own until page writes exist
+pub trait SaveStorage {
+    /// fills `data` with the stored save; a save which doesn't exist yet should be zeroed
+    fn load(&mut self, kind: SaveKind, data: &mut [u8]) -> Result<(), ()>;
+
+    fn store(&mut self, kind: SaveKind, data: &[u8]) -> Result<(), ()>;
+}
+
+/// the DRAM backing for an app's emulated saves, as described by the `__osBb*` globals
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Saves {
+    eeprom: (u32, u32),
+    flash: (u32, u32),
+    sram: (u32, u32),
+    paks: [u32; PAKS],
+    pak_size: u32,
+}
+
+impl Saves {
+    /// carves space for each save out of `region`, which is in physical addresses and has to be
+    /// kept clear of the app
+    pub fn allocate(layout: &SaveLayout, region: Range<u32>) -> Result<Self, SaveError> {
+        let mut next = region.start.next_multiple_of(SAVE_ALIGN);
+
+        let mut alloc = |size: u32| {
+            if size == 0 {
+                return Ok(0);
+            }
+
+            let addr = next;
+            next = (addr + size).next_multiple_of(SAVE_ALIGN);
+
+            if next > region.end {
+                Err(SaveError::NoSpace)
+            } else {
+                Ok(addr)
+            }
+        };
+
+        let mut saves = Self {
+            eeprom: (alloc(layout.eeprom_size)?, layout.eeprom_size),
+            flash: (alloc(layout.flash_size)?, layout.flash_size),
+            sram: (alloc(layout.sram_size)?, layout.sram_size),
+            paks: [0; PAKS],
+            pak_size: layout.pak_size,
+        };
+
+        for pak in saves.paks.iter_mut().take(layout.paks.min(PAKS)) {
+            *pak = alloc(layout.pak_size)?;
+        }
+
+        Ok(saves)
+    }
+
+    /// picks up where the saves were left by `publish`, which is how they're found again after
+    /// the app exits
+    pub fn from_globals() -> Self {
+        unsafe {
+            Self {
+                eeprom: (
+                    BB_EEPROM_ADDRESS.read_volatile(),
+                    BB_EEPROM_SIZE.read_volatile(),
+                ),
+                flash: (
+                    BB_FLASH_ADDRESS.read_volatile(),
+                    BB_FLASH_SIZE.read_volatile(),
+                ),
+                sram: (
+                    BB_SRAM_ADDRESS.read_volatile(),
+                    BB_SRAM_SIZE.read_volatile(),
+                ),
+                paks: core::array::from_fn(|i| (&raw const (*BB_PAK_ADDRESS)[i]).read_volatile()),
+                pak_size: BB_PAK_SIZE.read_volatile(),
+            }
+        }
+    }
+
+    /// tells the app where its saves are; call this just before launching it
+    pub fn publish(&self) {
+        unsafe {
+            BB_EEPROM_ADDRESS.write_volatile(self.eeprom.0);
+            BB_EEPROM_SIZE.write_volatile(self.eeprom.1);
+            BB_FLASH_ADDRESS.write_volatile(self.flash.0);
+            BB_FLASH_SIZE.write_volatile(self.flash.1);
+            BB_SRAM_ADDRESS.write_volatile(self.sram.0);
+            BB_SRAM_SIZE.write_volatile(self.sram.1);
+            for (i, &pak) in self.paks.iter().enumerate() {
+                (&raw mut (*BB_PAK_ADDRESS)[i]).write_volatile(pak);
+            }
+            BB_PAK_SIZE.write_volatile(self.pak_size);
+            BB_STATE_DIRTY.write_volatile(0);
+        }
+    }
+
+    fn each(&self) -> impl Iterator<Item = (SaveKind, u32, u32)> + '_ {
+        [
+            (SaveKind::Eeprom, self.eeprom.0, self.eeprom.1),
+            (SaveKind::Flash, self.flash.0, self.flash.1),
+            (SaveKind::Sram, self.sram.0, self.sram.1),
+        ]
+        .into_iter()
+        .chain(
+            self.paks
+                .iter()
+                .enumerate()
+                .map(|(i, &addr)| (SaveKind::Pak(i), addr, self.pak_size)),
+        )
+        .filter(|&(_, addr, size)| addr != 0 && size != 0)
+    }
+
+    // accessed uncached, so nothing is left sitting in the cache when the app starts
+    fn backing(addr: u32, size: u32) -> &'static mut [u8] {
+        unsafe {
+            from_raw_parts_mut(
+                with_exposed_provenance_mut(phys_to_k1_usize(addr as usize)),
+                size as usize,
+            )
+        }
+    }
+
+    pub fn load(&self, storage: &mut (impl SaveStorage + ?Sized)) -> Result<(), SaveError> {
+        for (kind, addr, size) in self.each() {
+            storage
+                .load(kind, Self::backing(addr, size))
+                .map_err(|_| SaveError::Storage)?;
+        }
+
+        Ok(())
+    }
+
+    /// whether the app has written to any of its saves since they were published
+    pub fn is_dirty() -> bool {
+        unsafe { BB_STATE_DIRTY.read_volatile() != 0 }
+    }
+
+    /// stores the saves if the app changed them, returning whether it did
+    pub fn write_back(&self, storage: &mut (impl SaveStorage + ?Sized)) -> Result<bool, SaveError> {
+        if !Self::is_dirty() {
+            return Ok(false);
+        }
+
+        for (kind, addr, size) in self.each() {
+            // the app may have left some of its writes in the cache
+            data_cache_writeback_raw(
+                phys_to_k0_usize(addr as usize),
+                phys_to_k0_usize((addr + size) as usize),
+            );
+
+            storage
+                .store(kind, Self::backing(addr, size))
+                .map_err(|_| SaveError::Storage)?;
+        }
+
+        // only once everything is safely stored, so a failure can be retried
+        unsafe { BB_STATE_DIRTY.write_volatile(0) }
+
+        Ok(true)
+    }
+}
@@ -11,7 +11,7 @@ mod sk;
 #[cfg(not(feature = "sk"))]
 pub use n64::{globals, interrupts};
 #[cfg(feature = "sk")]
-pub use sk::launch_app;
+pub use sk::{launch_app, launch_app_with_saves, write_back_saves};
 
 //use crate::util::show;
 
@@ -1,5 +1,9 @@
 use volcell::VolatileCell;
 
+use crate::boot::is_bbplayer;
+use crate::gpio::BoardRevision;
+use crate::rom::{CicType, RomHeader, TvType, ROM_BASE};
+use crate::util::phys_to_k1_u32;
 use crate::{mi::mi, pi::pi};
 
 extern "C" {
@@ -34,13 +38,29 @@ pub(super) fn setup_globals() {
     let pi = pi();
     unsafe {
         (&raw mut __osBbIsBb).write_volatile(if mi.version() & 0xF0 == 0xB0 {
-            if pi.bb_gpio() & 0xC0000000 != 0 {
-                2
-            } else {
-                1
+            match pi.gpio().board_revision() {
+                BoardRevision::Original => 1,
+                BoardRevision::Revised(_) => 2,
             }
         } else {
             0
         });
     }
+
+    // the BB's launcher fills these in itself
+    if !is_bbplayer() {
+        let header = RomHeader::read(pi);
+        let cic = CicType::detect(pi);
+
+        unsafe {
+            (&raw mut osRomBase).write_volatile(phys_to_k1_u32(ROM_BASE));
+            (&raw mut osCicId).write_volatile(cic.id());
+
+            // IPL3 stores the console's own TV type, which is what matters for the video mode; the
+            // ROM's region is only a guess for when that's missing
+            if (&raw const osTvType).read_volatile() > TvType::Mpal as u32 {
+                (&raw mut osTvType).write_volatile(header.tv_type() as u32);
+            }
+        }
+    }
 }
@@ -386,16 +386,19 @@ extern "C" fn int_handler() {
 
         macro_rules! call_handler {
             ($s:expr, $name:literal, $ex:path, $fn:expr, $b:block) => {
-                #[cfg(not(debug_assertions))]
-                let fun = $fn.unwrap();
-                #[cfg(debug_assertions)]
-                let fun = $fn.expect(concat!(
-                    $name,
-                    " interrupt is enabled, so there should be a handler installed"
-                ));
-
-                if $s & $ex(true) != 0 && fun() {
-                    $b
+                // only interrupts that are pending have to have a handler
+                if $s & $ex(true) != 0 {
+                    #[cfg(not(debug_assertions))]
+                    let fun = $fn.unwrap();
+                    #[cfg(debug_assertions)]
+                    let fun = $fn.expect(concat!(
+                        $name,
+                        " interrupt is enabled, so there should be a handler installed"
+                    ));
+
+                    if fun() {
+                        $b
+                    }
                 }
             };
         }
@@ -417,7 +420,7 @@ extern "C" fn int_handler() {
             });
 
             call_handler!(status, "IDE", BBInterrupt::ide, im.ide_fn, {
-                panic!("Unhandled BB interrupt: IDE");
+                crate::ide::acknowledge_interrupt();
             });
 
             call_handler!(status, "PI error", BBInterrupt::pi_err, im.pi_err_fn, {
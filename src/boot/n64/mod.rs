@@ -1,5 +1,5 @@
 use core::arch::naked_asm;
-use core::array;
+use core::fmt::Write;
 use core::mem::MaybeUninit;
 use core::panic::PanicInfo;
 use core::ptr::{
@@ -15,25 +15,26 @@ pub mod globals;
 pub mod interrupts;
 
 use super::is_bbplayer;
-use globals::{osTvType, setup_globals};
+use globals::setup_globals;
 use interrupts::setup_ints;
 
+use crate::isviewer::IsViewer;
+use crate::pi::pi;
+use crate::probe::DebugWriter;
 use crate::si::si;
 use crate::text::Colour;
 use crate::util::phys_to_k1_usize;
-use crate::vi::{vi, Mode};
+use crate::vi::vi;
 
 const IPL3_SIZE: usize = 0x1000 - 0x40;
 
-fn to_base_10<const N: usize>(num: u32) -> ([u8; N], usize) {
-    let arr =
-        array::from_fn::<_, N, _>(|i| ((num / (10u32.pow((N - i - 1) as _))) % 10) as u8 + b'0');
-    let index = arr
-        .iter()
-        .enumerate()
-        .find_map(|(i, &e)| if e != 0 { Some(i) } else { None })
-        .unwrap_or(N - 1);
-    (arr, index)
+#[link_section = ".boot"]
+fn write_panic(out: &mut impl Write, info: &PanicInfo) {
+    let _ = write!(out, "Panic\n\n");
+    if let Some(loc) = info.location() {
+        let _ = write!(out, "{}:{}:{}\n\n", loc.file(), loc.line(), loc.column());
+    }
+    let _ = write!(out, "{}\n\n", info.message());
 }
 
 #[link_section = ".boot"]
@@ -42,35 +43,21 @@ fn panic(_info: &PanicInfo) -> ! {
     let si = si();
 
     si.init_hw();
-    si.txrx(b"Panic\n\n", None);
-    if let Some(loc) = _info.location() {
-        si.txrx(loc.file().as_bytes(), None);
-        si.txrx(b":", None);
-        let line = loc.line();
-        let (line_buf, line_start) = to_base_10::<10>(line);
-        si.txrx(&line_buf[line_start..], None);
-        si.txrx(b":", None);
-        let column = loc.column();
-        let (column_buf, column_start) = to_base_10::<10>(column);
-        si.txrx(&column_buf[column_start..], None);
-        si.txrx(b"\n\n", None);
-    }
-    if let Some(msg) = _info.message().as_str() {
-        si.txrx(msg.as_bytes(), None);
-        si.txrx(b"\n\n", None);
+
+    write_panic(&mut DebugWriter::new(si), _info);
+
+    // emulators show this in their log, so it's the easiest place to find without hardware
+    if !is_bbplayer() {
+        if let Some(mut isv) = IsViewer::detect(pi()) {
+            write_panic(&mut isv, _info);
+        }
     }
 
     let vi = vi();
 
     //let mut buf = [0; 0x100];
 
-    #[allow(static_mut_refs)]
-    vi.init(match unsafe { (&raw const osTvType).read_volatile() } {
-        0 => Mode::PAL60,
-        1 => Mode::NTSC,
-        2 => todo!(),
-        _ => unreachable!(),
-    });
+    vi.init_auto();
     vi.clear_framebuffer();
 
     let mut line = 1;
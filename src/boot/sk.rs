@@ -1,8 +1,11 @@
+use core::convert::Infallible;
 use core::ffi::c_void;
+use core::ops::Range;
 use core::panic::PanicInfo;
 use core::ptr::{from_raw_parts, from_raw_parts_mut};
 use core::{arch::asm, mem::size_of};
 
+use crate::bbsave::{SaveError, SaveLayout, SaveStorage, Saves};
 use crate::cop0::{DiagStatus, Status};
 use crate::mi::mi;
 use crate::pi::pi;
@@ -433,6 +436,8 @@ unsafe extern "C" fn handle_timer_expiry() {
 unsafe extern "C" fn handle_other() {
     if mi().bb_secure_exception() & (1 << 6) != 0 {
         // button
+        // a failed write back leaves the saves dirty in DRAM, which is all that can be done here
+        let _ = write_back_saves();
         startup();
     }
     panic!("unhandled entry type");
@@ -458,6 +463,41 @@ unsafe extern "C" fn _int_handler_cached() {
     panic!("interrupt");
 }
 
+// the running app's saves and where they go back to, which the app can't touch since they're in
+// the SK's own memory
+static mut APP_SAVES: Option<(Saves, &'static mut dyn SaveStorage)> = None;
+
+/// loads an app's saves from `storage` into `region` and tells the app where they are, then
+/// launches it
+///
+/// the saves go back to `storage` when the app is left with the power button, or when
+/// `write_back_saves` is called from the exit skc; only returns if the saves couldn't be set up
+pub unsafe fn launch_app_with_saves(
+    entry: unsafe extern "C" fn(u32) -> !,
+    layout: &SaveLayout,
+    region: Range<u32>,
+    storage: &'static mut dyn SaveStorage,
+) -> Result<Infallible, SaveError> {
+    let saves = Saves::allocate(layout, region)?;
+    saves.load(storage)?;
+    saves.publish();
+
+    (&raw mut APP_SAVES).write(Some((saves, storage)));
+
+    launch_app(entry)
+}
+
+/// stores the last launched app's saves if it changed them, returning whether it did
+///
+/// if storing fails, `__osBbStateDirty` is left set and the saves can still be found with
+/// `Saves::from_globals`
+pub fn write_back_saves() -> Result<bool, SaveError> {
+    match unsafe { (&raw mut APP_SAVES).replace(None) } {
+        Some((saves, storage)) => saves.write_back(storage),
+        None => Ok(false),
+    }
+}
+
 #[naked]
 pub unsafe extern "C" fn launch_app(entry: unsafe extern "C" fn(u32) -> !) -> ! {
     asm!(
@@ -0,0 +1,827 @@
+use core::ptr::from_raw_parts_mut;
+
+use crate::boot::ms_to_ticks;
+use crate::cop0::cop0;
+use crate::io_ptr;
+use crate::pi::{Domain, DomainTiming, Pi};
+use crate::rtc::{from_bcd, to_bcd, DateTime};
+use crate::types::Align8;
+
+const DD_BASE: u32 = 0x0500_0000;
+
+// parity for the block being read or written, one sector of it per C2 sector
+const DD_C2_BUFFER: u32 = DD_BASE;
+// the sector the buffer manager is waiting for us to take or give it
+const DD_SECTOR_BUFFER: u32 = DD_BASE + 0x0400;
+
+const ASIC_DATA: *mut u32 = io_ptr!(mut DD_BASE + 0x0500);
+// writes issue a command, reads return the status
+const ASIC_CMD_STATUS: *mut u32 = io_ptr!(mut DD_BASE + 0x0508);
+const ASIC_CUR_TK: *mut u32 = io_ptr!(mut DD_BASE + 0x050C);
+// writes control the buffer manager, reads return its status and acknowledge its interrupt
+const ASIC_BM_CTL_STATUS: *mut u32 = io_ptr!(mut DD_BASE + 0x0510);
+const ASIC_HARD_RESET: *mut u32 = io_ptr!(mut DD_BASE + 0x0520);
+const ASIC_HOST_SECBYTE: *mut u32 = io_ptr!(mut DD_BASE + 0x0528);
+const ASIC_SEC_BYTE: *mut u32 = io_ptr!(mut DD_BASE + 0x0530);
+const ASIC_ID: *mut u32 = io_ptr!(mut DD_BASE + 0x0540);
+
+// the top halfword of `ASIC_ID`; anything else is open bus, i.e. no drive
+const ID_RETAIL: u32 = 0x0003;
+const ID_DEVELOPMENT: u32 = 0x0004;
+
+const HARD_RESET_KEY: u32 = 0xAAAA_0000;
+
+const CMD_SEEK_READ: u16 = 0x01;
+const CMD_SEEK_WRITE: u16 = 0x02;
+const CMD_RECALIBRATE: u16 = 0x03;
+const CMD_SLEEP: u16 = 0x04;
+const CMD_START: u16 = 0x05;
+const CMD_CLEAR_DISK_CHANGE: u16 = 0x08;
+const CMD_CLEAR_RESET: u16 = 0x09;
+const CMD_READ_VERSION: u16 = 0x0A;
+const CMD_STANDBY: u16 = 0x0D;
+const CMD_SET_YEAR_MONTH: u16 = 0x0F;
+const CMD_SET_DAY_HOUR: u16 = 0x10;
+const CMD_SET_MINUTE_SECOND: u16 = 0x11;
+const CMD_GET_YEAR_MONTH: u16 = 0x12;
+const CMD_GET_DAY_HOUR: u16 = 0x13;
+const CMD_GET_MINUTE_SECOND: u16 = 0x14;
+
+// the head goes in the seek commands' data, above the track
+const SEEK_HEAD_SHIFT: u16 = 12;
+
+const STATUS_DISK_CHANGE: u32 = 0x0001_0000;
+const STATUS_MECHA_ERROR: u32 = 0x0002_0000;
+const STATUS_WRITE_PROTECT_ERROR: u32 = 0x0004_0000;
+const STATUS_HEAD_RETRACTED: u32 = 0x0008_0000;
+const STATUS_MOTOR_STOPPED: u32 = 0x0010_0000;
+const STATUS_RESET: u32 = 0x0040_0000;
+const STATUS_BUSY: u32 = 0x0080_0000;
+const STATUS_DISK_PRESENT: u32 = 0x0100_0000;
+const STATUS_MECHA_INT: u32 = 0x0200_0000;
+const STATUS_BM_INT: u32 = 0x0400_0000;
+const STATUS_BM_ERROR: u32 = 0x0800_0000;
+const STATUS_C2_TRANSFER: u32 = 0x1000_0000;
+const STATUS_DATA_REQUEST: u32 = 0x4000_0000;
+
+// both must be set in `ASIC_CUR_TK` once the head has settled on the track
+const TRACK_INDEX_LOCK: u32 = 0x6000_0000;
+
+const BM_MECHA_INT_RESET: u32 = 0x0100_0000;
+const BM_RESET: u32 = 0x1000_0000;
+const BM_MODE_READ: u32 = 0x4000_0000;
+const BM_START: u32 = 0x8000_0000;
+// the first sector to transfer
+const BM_SECTOR_SHIFT: u32 = 16;
+
+const BM_STATUS_C1_ERROR: u32 = 0x0001_0000;
+const BM_STATUS_ERROR: u32 = 0x0400_0000;
+
+// spinning up from a standstill is what takes the longest
+const COMMAND_TIMEOUT_MS: u32 = 10_000;
+const SECTOR_TIMEOUT_MS: u32 = 1_000;
+
+pub const SECTORS_PER_BLOCK: usize = 85;
+pub const C2_SECTORS: usize = 4;
+// how the drive numbers sectors, counting parity and gaps; the second block starts at this one
+const SECTORS_PER_BLOCK_RAW: usize = 90;
+pub const BLOCKS_PER_TRACK: usize = 2;
+
+pub const MAX_SECTOR_SIZE: usize = 232;
+pub const MAX_BLOCK_SIZE: usize = MAX_SECTOR_SIZE * SECTORS_PER_BLOCK;
+
+pub const ZONES: usize = 16;
+// each zone keeps this many tracks back to stand in for defective ones
+const SPARE_TRACKS: usize = 12;
+// zones 0-7 are on head 0, outside in; zones 8-15 on head 1 cover the same tracks as zones 0-7
+const ZONE_TRACKS: [usize; ZONES / 2] = [158, 158, 149, 149, 149, 149, 149, 114];
+const ZONE_SECTOR_SIZE: [usize; ZONES] = [
+    232, 216, 208, 192, 176, 160, 144, 128, 216, 208, 192, 176, 160, 144, 128, 112,
+];
+
+pub const DISK_TYPES: usize = 7;
+// the order each disk type's LBAs visit the zones in; the higher the type, the more is ROM
+const DISK_TYPE_ZONES: [[u8; ZONES]; DISK_TYPES] = [
+    [0, 1, 2, 9, 8, 3, 4, 5, 6, 7, 15, 14, 13, 12, 11, 10],
+    [0, 1, 2, 3, 10, 9, 8, 4, 5, 6, 7, 15, 14, 13, 12, 11],
+    [0, 1, 2, 3, 4, 11, 10, 9, 8, 5, 6, 7, 15, 14, 13, 12],
+    [0, 1, 2, 3, 4, 5, 12, 11, 10, 9, 8, 6, 7, 15, 14, 13],
+    [0, 1, 2, 3, 4, 5, 6, 13, 12, 11, 10, 9, 8, 7, 15, 14],
+    [0, 1, 2, 3, 4, 5, 6, 7, 14, 13, 12, 11, 10, 9, 8, 15],
+    [0, 1, 2, 3, 4, 5, 6, 7, 15, 14, 13, 12, 11, 10, 9, 8],
+];
+
+// the system area is the first tracks of zone 0, and isn't subject to defect mapping
+const SYSTEM_LBAS_RETAIL: [u32; 4] = [0, 1, 8, 9];
+const SYSTEM_LBAS_DEVELOPMENT: [u32; 4] = [2, 3, 10, 11];
+const SYSTEM_SECTOR_SIZE_DEVELOPMENT: usize = 192;
+const SYSTEM_DATA_SIZE: usize = 0xE8;
+const SYSTEM_FORMAT: u8 = 0x10;
+const DEFECTS_SIZE: usize = 0xC0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DdError {
+    NoDisk,
+    /// the disk was swapped since the last `clear_disk_change`
+    DiskChanged,
+    /// the drive was still busy when the timeout ran out
+    Timeout,
+    /// the drive reported a mechanical error, e.g. a failed seek
+    Mechanical,
+    WriteProtected,
+    OutOfRange,
+    /// more sectors in the block had errors than the C2 parity can rebuild
+    Uncorrectable,
+    /// none of the copies of the system area could be read, or they were nonsense
+    BadSystemArea,
+    InvalidTime,
+}
+
+/// the drive's status register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DdStatus(pub u32);
+
+impl DdStatus {
+    pub fn disk_present(self) -> bool {
+        self.0 & STATUS_DISK_PRESENT != 0
+    }
+
+    pub fn disk_changed(self) -> bool {
+        self.0 & STATUS_DISK_CHANGE != 0
+    }
+
+    pub fn busy(self) -> bool {
+        self.0 & STATUS_BUSY != 0
+    }
+
+    /// set after power-on or a hard reset, until `CMD_CLEAR_RESET`
+    pub fn reset(self) -> bool {
+        self.0 & STATUS_RESET != 0
+    }
+
+    pub fn motor_stopped(self) -> bool {
+        self.0 & STATUS_MOTOR_STOPPED != 0
+    }
+
+    pub fn head_retracted(self) -> bool {
+        self.0 & STATUS_HEAD_RETRACTED != 0
+    }
+
+    fn has(self, mask: u32) -> bool {
+        self.0 & mask != 0
+    }
+}
+
+/// where a block is on the disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub track: u16,
+    pub head: u8,
+    pub block: u8,
+    pub zone: u8,
+}
+
+impl Location {
+    pub fn sector_size(&self) -> usize {
+        ZONE_SECTOR_SIZE[self.zone as usize]
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.sector_size() * SECTORS_PER_BLOCK
+    }
+
+    /// the system area comes before any of the zone maths, in zone 0
+    fn system(lba: u32) -> Self {
+        Self {
+            track: (lba / BLOCKS_PER_TRACK as u32) as u16,
+            head: 0,
+            block: start_block(lba),
+            zone: 0,
+        }
+    }
+}
+
+// the first block of every other pair of tracks is the second one, to save a revolution going
+// from one track to the next
+fn start_block(lba: u32) -> u8 {
+    match lba & 3 {
+        0 | 3 => 0,
+        _ => 1,
+    }
+}
+
+fn zone_lbas(zone: usize) -> u32 {
+    ((ZONE_TRACKS[zone % (ZONES / 2)] - SPARE_TRACKS) * BLOCKS_PER_TRACK) as u32
+}
+
+fn zone_start_track(zone: usize) -> usize {
+    ZONE_TRACKS[..zone % (ZONES / 2)].iter().sum()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiskRegion {
+    Japan,
+    Usa,
+    Development,
+    Unknown(u32),
+}
+
+impl DiskRegion {
+    fn from_word(word: u32) -> Self {
+        match word {
+            0xE848_D316 => Self::Japan,
+            0x2263_EE56 => Self::Usa,
+            0x0000_0000 => Self::Development,
+            _ => Self::Unknown(word),
+        }
+    }
+}
+
+/// the disk's format information, which every LBA is mapped through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SystemArea {
+    pub region: DiskRegion,
+    /// decides which zones are ROM and which are RAM, and the order they're in
+    pub disk_type: u8,
+    /// how many blocks of the disk the IPL loads at boot
+    pub ipl_blocks: u16,
+    pub ipl_load_address: u32,
+    pub rom_end_lba: u16,
+    pub ram_start_lba: u16,
+    pub ram_end_lba: u16,
+    // where each zone's list of defective tracks ends in `defects`
+    defect_ends: [u8; ZONES],
+    defects: [u8; DEFECTS_SIZE],
+}
+
+impl SystemArea {
+    pub fn parse(data: &[u8; SYSTEM_DATA_SIZE]) -> Result<Self, DdError> {
+        let half = |i: usize| u16::from_be_bytes([data[i], data[i + 1]]);
+        let word = |i: usize| u32::from_be_bytes(data[i..i + 4].try_into().unwrap());
+
+        let disk_type = data[0x05] & 0x0F;
+
+        if data[0x04] != SYSTEM_FORMAT || data[0x05] & 0xF0 != 0x10 {
+            return Err(DdError::BadSystemArea);
+        }
+
+        if disk_type as usize >= DISK_TYPES {
+            return Err(DdError::BadSystemArea);
+        }
+
+        let area = Self {
+            region: DiskRegion::from_word(word(0x00)),
+            disk_type,
+            ipl_blocks: half(0x06),
+            ipl_load_address: word(0x1C),
+            rom_end_lba: half(0xE0),
+            ram_start_lba: half(0xE2),
+            ram_end_lba: half(0xE4),
+            defect_ends: data[0x08..0x18].try_into().unwrap(),
+            defects: data[0x20..0xE0].try_into().unwrap(),
+        };
+
+        let mut start = 0;
+        for &end in &area.defect_ends {
+            if end < start || end as usize > DEFECTS_SIZE {
+                return Err(DdError::BadSystemArea);
+            }
+            start = end;
+        }
+
+        Ok(area)
+    }
+
+    /// the track offsets within `zone` which are skipped, in ascending order
+    pub fn defects(&self, zone: usize) -> &[u8] {
+        let start = if zone == 0 {
+            0
+        } else {
+            self.defect_ends[zone - 1]
+        };
+
+        &self.defects[start as usize..self.defect_ends[zone] as usize]
+    }
+
+    pub fn lba_count(&self) -> u32 {
+        (0..ZONES).map(zone_lbas).sum()
+    }
+
+    pub fn locate(&self, lba: u32) -> Result<Location, DdError> {
+        let mut rest = lba;
+
+        for &zone in &DISK_TYPE_ZONES[self.disk_type as usize] {
+            let zone = zone as usize;
+            let lbas = zone_lbas(zone);
+
+            if rest >= lbas {
+                rest -= lbas;
+                continue;
+            }
+
+            let mut offset = rest as usize / BLOCKS_PER_TRACK;
+
+            // each defective track at or before the one we want pushes it along one
+            for &defect in self.defects(zone) {
+                if defect as usize <= offset {
+                    offset += 1;
+                }
+            }
+
+            let start = zone_start_track(zone);
+            let tracks = ZONE_TRACKS[zone % (ZONES / 2)];
+
+            // head 1 works its way back out, so LBAs carry on from the inside of the zone
+            let (track, head) = if zone < ZONES / 2 {
+                (start + offset, 0)
+            } else {
+                (start + tracks - 1 - offset, 1)
+            };
+
+            return Ok(Location {
+                track: track as u16,
+                head,
+                block: start_block(lba),
+                zone: zone as u8,
+            });
+        }
+
+        Err(DdError::OutOfRange)
+    }
+
+    pub fn is_writable(&self, lba: u32) -> bool {
+        (self.ram_start_lba as u32..=self.ram_end_lba as u32).contains(&lba)
+    }
+}
+
+/// sectors of a block which the drive read with errors; as long as there are no more than
+/// `C2_SECTORS` of them, the caller can rebuild them from the block's C2 parity
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Erasures {
+    count: usize,
+    sectors: [u8; C2_SECTORS],
+}
+
+impl Erasures {
+    pub fn sectors(&self) -> &[u8] {
+        &self.sectors[..self.count]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+}
+
+pub struct Dd {
+    id: u32,
+    // the last seek, so reading a track's blocks in turn doesn't seek every time
+    position: Option<(u16, u8, bool)>,
+    c2: Align8<[u8; C2_SECTORS * MAX_SECTOR_SIZE]>,
+}
+
+impl Dd {
+    /// configures domain 2 for the drive and checks that there's one there
+    pub fn detect(pi: &mut Pi) -> Option<Self> {
+        pi.set_domain_timing(Domain::Dom2, DomainTiming::DD);
+
+        pi.wait();
+
+        let id = unsafe { ASIC_ID.read_volatile() } >> 16;
+
+        if id != ID_RETAIL && id != ID_DEVELOPMENT {
+            return None;
+        }
+
+        let mut dd = Self {
+            id,
+            position: None,
+            c2: Align8([0; C2_SECTORS * MAX_SECTOR_SIZE]),
+        };
+
+        if dd.status(pi).reset() {
+            dd.command(pi, CMD_CLEAR_RESET, 0).ok()?;
+        }
+
+        Some(dd)
+    }
+
+    pub fn is_development(&self) -> bool {
+        self.id == ID_DEVELOPMENT
+    }
+
+    fn read_reg(pi: &mut Pi, reg: *mut u32) -> u32 {
+        pi.wait();
+
+        unsafe { reg.read_volatile() }
+    }
+
+    fn write_reg(pi: &mut Pi, reg: *mut u32, val: u32) {
+        pi.wait();
+
+        unsafe { reg.write_volatile(val) }
+    }
+
+    pub fn status(&self, pi: &mut Pi) -> DdStatus {
+        DdStatus(Self::read_reg(pi, ASIC_CMD_STATUS))
+    }
+
+    fn wait_for(pi: &mut Pi, mask: u32, timeout_ms: u32) -> Result<DdStatus, DdError> {
+        let cop0 = cop0();
+
+        let start = cop0.count();
+        let timeout = ms_to_ticks(timeout_ms);
+
+        loop {
+            let status = DdStatus(Self::read_reg(pi, ASIC_CMD_STATUS));
+
+            if status.has(mask) {
+                return Ok(status);
+            }
+
+            if cop0.count().wrapping_sub(start) >= timeout {
+                return Err(DdError::Timeout);
+            }
+        }
+    }
+
+    /// issues a command and waits for the drive to finish it, returning its response
+    fn command(&mut self, pi: &mut Pi, cmd: u16, data: u16) -> Result<u16, DdError> {
+        Self::write_reg(pi, ASIC_DATA, (data as u32) << 16);
+        Self::write_reg(pi, ASIC_CMD_STATUS, (cmd as u32) << 16);
+
+        let status = Self::wait_for(pi, STATUS_MECHA_INT, COMMAND_TIMEOUT_MS)?;
+
+        Self::write_reg(pi, ASIC_BM_CTL_STATUS, BM_MECHA_INT_RESET);
+
+        if status.has(STATUS_MECHA_ERROR) {
+            self.position = None;
+            return Err(DdError::Mechanical);
+        }
+
+        Ok((Self::read_reg(pi, ASIC_DATA) >> 16) as u16)
+    }
+
+    /// resets the whole drive, as if it had just been powered on
+    pub fn hard_reset(&mut self, pi: &mut Pi) -> Result<(), DdError> {
+        Self::write_reg(pi, ASIC_HARD_RESET, HARD_RESET_KEY);
+
+        self.position = None;
+
+        Self::wait_for(pi, STATUS_RESET, COMMAND_TIMEOUT_MS)?;
+
+        self.command(pi, CMD_CLEAR_RESET, 0).map(|_| ())
+    }
+
+    /// the drive's firmware version
+    pub fn version(&mut self, pi: &mut Pi) -> Result<u16, DdError> {
+        self.command(pi, CMD_READ_VERSION, 0)
+    }
+
+    /// acknowledges a disk swap, after which the new disk can be used
+    pub fn clear_disk_change(&mut self, pi: &mut Pi) -> Result<(), DdError> {
+        self.position = None;
+
+        self.command(pi, CMD_CLEAR_DISK_CHANGE, 0).map(|_| ())
+    }
+
+    fn check_disk(&self, pi: &mut Pi) -> Result<(), DdError> {
+        let status = self.status(pi);
+
+        if !status.disk_present() {
+            Err(DdError::NoDisk)
+        } else if status.disk_changed() {
+            Err(DdError::DiskChanged)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn start_motor(&mut self, pi: &mut Pi) -> Result<(), DdError> {
+        self.check_disk(pi)?;
+
+        self.command(pi, CMD_START, 0).map(|_| ())
+    }
+
+    /// parks the head, leaving the motor spinning
+    pub fn standby(&mut self, pi: &mut Pi) -> Result<(), DdError> {
+        self.position = None;
+
+        self.command(pi, CMD_STANDBY, 0).map(|_| ())
+    }
+
+    /// parks the head and stops the motor
+    pub fn sleep(&mut self, pi: &mut Pi) -> Result<(), DdError> {
+        self.position = None;
+
+        self.command(pi, CMD_SLEEP, 0).map(|_| ())
+    }
+
+    /// moves the head back to track 0, which can get a drive out of a mechanical error
+    pub fn recalibrate(&mut self, pi: &mut Pi) -> Result<(), DdError> {
+        self.position = None;
+
+        self.command(pi, CMD_RECALIBRATE, 0).map(|_| ())
+    }
+
+    pub fn seek(&mut self, pi: &mut Pi, loc: &Location, write: bool) -> Result<(), DdError> {
+        if self.position == Some((loc.track, loc.head, write)) {
+            return Ok(());
+        }
+
+        self.check_disk(pi)?;
+
+        let cmd = if write { CMD_SEEK_WRITE } else { CMD_SEEK_READ };
+
+        self.position = None;
+
+        self.command(pi, cmd, loc.track | ((loc.head as u16) << SEEK_HEAD_SHIFT))?;
+
+        if Self::read_reg(pi, ASIC_CUR_TK) & TRACK_INDEX_LOCK != TRACK_INDEX_LOCK {
+            return Err(DdError::Mechanical);
+        }
+
+        self.position = Some((loc.track, loc.head, write));
+
+        Ok(())
+    }
+
+    fn bounce(buf: &mut Align8<[u8; 0x100]>, len: usize) -> &mut Align8<[u8]> {
+        unsafe { &mut *from_raw_parts_mut::<Align8<[u8]>>(buf.0.as_mut_ptr() as *mut (), len) }
+    }
+
+    /// points the buffer manager at the start of a block
+    fn start_transfer(pi: &mut Pi, sector_size: usize, block: u8, mode: u32) {
+        let size = sector_size as u32;
+
+        Self::write_reg(pi, ASIC_HOST_SECBYTE, (size - 1) << 16);
+        Self::write_reg(
+            pi,
+            ASIC_SEC_BYTE,
+            ((size + 7) << 24) | ((SECTORS_PER_BLOCK_RAW as u32 - 1) << 16),
+        );
+
+        Self::write_reg(pi, ASIC_BM_CTL_STATUS, BM_RESET);
+        Self::write_reg(pi, ASIC_BM_CTL_STATUS, 0);
+
+        let sector = block as u32 * SECTORS_PER_BLOCK_RAW as u32;
+
+        Self::write_reg(
+            pi,
+            ASIC_BM_CTL_STATUS,
+            BM_START | mode | (sector << BM_SECTOR_SHIFT),
+        );
+    }
+
+    /// reads the block at `loc` with sectors of `sector_size` bytes, passing each to `f` along
+    /// with whether the drive read it cleanly
+    fn read_sectors(
+        &mut self,
+        pi: &mut Pi,
+        loc: &Location,
+        sector_size: usize,
+        mut f: impl FnMut(usize, &[u8], bool),
+    ) -> Result<Erasures, DdError> {
+        self.seek(pi, loc, false)?;
+
+        Self::start_transfer(pi, sector_size, loc.block, BM_MODE_READ);
+
+        let mut buf = Align8([0; 0x100]);
+        let mut erasures = Erasures::default();
+
+        for sector in 0..SECTORS_PER_BLOCK {
+            let status = Self::wait_for(pi, STATUS_DATA_REQUEST, SECTOR_TIMEOUT_MS)?;
+
+            if status.has(STATUS_BM_ERROR) {
+                self.position = None;
+                return Err(DdError::Mechanical);
+            }
+
+            pi.read_into(Self::bounce(&mut buf, sector_size), DD_SECTOR_BUFFER);
+
+            // reading the status also acknowledges the sector
+            let clean = Self::read_reg(pi, ASIC_BM_CTL_STATUS) & BM_STATUS_C1_ERROR == 0;
+
+            if !clean {
+                if erasures.count == C2_SECTORS {
+                    return Err(DdError::Uncorrectable);
+                }
+
+                erasures.sectors[erasures.count] = sector as u8;
+                erasures.count += 1;
+            }
+
+            f(sector, &buf.0[..sector_size], clean);
+        }
+
+        // the parity always follows the data, and has to be taken before the next block
+        Self::wait_for(pi, STATUS_C2_TRANSFER, SECTOR_TIMEOUT_MS)?;
+
+        let c2_len = C2_SECTORS * sector_size;
+        let c2 = unsafe {
+            &mut *from_raw_parts_mut::<Align8<[u8]>>(self.c2.0.as_mut_ptr() as *mut (), c2_len)
+        };
+        pi.read_into(c2, DD_C2_BUFFER);
+
+        if Self::read_reg(pi, ASIC_BM_CTL_STATUS) & BM_STATUS_ERROR != 0 {
+            self.position = None;
+            return Err(DdError::Mechanical);
+        }
+
+        Ok(erasures)
+    }
+
+    /// reads one block into `data`, which must be `loc.block_size()` bytes; the block's C2 parity
+    /// is kept for `c2` whether or not any sectors need it
+    pub fn read_block(
+        &mut self,
+        pi: &mut Pi,
+        loc: &Location,
+        data: &mut [u8],
+    ) -> Result<Erasures, DdError> {
+        let size = loc.sector_size();
+
+        if data.len() != loc.block_size() {
+            return Err(DdError::OutOfRange);
+        }
+
+        self.read_sectors(pi, loc, size, |sector, buf, _| {
+            data[sector * size..(sector + 1) * size].copy_from_slice(buf);
+        })
+    }
+
+    /// the C2 parity of the last block read, one sector after another
+    pub fn c2(&self, loc: &Location) -> &[u8] {
+        &self.c2.0[..C2_SECTORS * loc.sector_size()]
+    }
+
+    /// writes one block from `data`, which must be `loc.block_size()` bytes; the drive generates
+    /// the C2 parity itself
+    pub fn write_block(&mut self, pi: &mut Pi, loc: &Location, data: &[u8]) -> Result<(), DdError> {
+        let size = loc.sector_size();
+
+        if data.len() != loc.block_size() {
+            return Err(DdError::OutOfRange);
+        }
+
+        self.seek(pi, loc, true)?;
+
+        Self::start_transfer(pi, size, loc.block, 0);
+
+        let mut buf = Align8([0; 0x100]);
+
+        for chunk in data.chunks_exact(size) {
+            let status = Self::wait_for(pi, STATUS_DATA_REQUEST, SECTOR_TIMEOUT_MS)?;
+
+            if status.has(STATUS_WRITE_PROTECT_ERROR) {
+                return Err(DdError::WriteProtected);
+            }
+
+            buf.0[..size].copy_from_slice(chunk);
+            pi.write(Self::bounce(&mut buf, size), DD_SECTOR_BUFFER);
+
+            Self::read_reg(pi, ASIC_BM_CTL_STATUS);
+        }
+
+        let status = Self::wait_for(pi, STATUS_BM_INT, SECTOR_TIMEOUT_MS)?;
+
+        let bm_status = Self::read_reg(pi, ASIC_BM_CTL_STATUS);
+
+        if status.has(STATUS_WRITE_PROTECT_ERROR) {
+            Err(DdError::WriteProtected)
+        } else if status.has(STATUS_BM_ERROR) || bm_status & BM_STATUS_ERROR != 0 {
+            self.position = None;
+            Err(DdError::Mechanical)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// tries each copy of the system area in turn; retail disks have theirs in 232-byte sectors
+    /// and development disks in 192-byte ones
+    pub fn read_system_area(&mut self, pi: &mut Pi) -> Result<SystemArea, DdError> {
+        let copies = SYSTEM_LBAS_RETAIL
+            .iter()
+            .map(|&lba| (lba, ZONE_SECTOR_SIZE[0]))
+            .chain(
+                SYSTEM_LBAS_DEVELOPMENT
+                    .iter()
+                    .map(|&lba| (lba, SYSTEM_SECTOR_SIZE_DEVELOPMENT)),
+            );
+
+        for (lba, sector_size) in copies {
+            let mut data = None;
+
+            // every sector holds the same thing, so take the first clean one
+            let read =
+                self.read_sectors(pi, &Location::system(lba), sector_size, |_, buf, clean| {
+                    if clean && data.is_none() {
+                        data = Some(buf[..SYSTEM_DATA_SIZE].try_into().unwrap());
+                    }
+                });
+
+            if let (Ok(_), Some(data)) = (read, data) {
+                if let Ok(area) = SystemArea::parse(&data) {
+                    return Ok(area);
+                }
+            }
+        }
+
+        Err(DdError::BadSystemArea)
+    }
+
+    pub fn read_lba(
+        &mut self,
+        pi: &mut Pi,
+        system: &SystemArea,
+        lba: u32,
+        data: &mut [u8],
+    ) -> Result<Erasures, DdError> {
+        let loc = system.locate(lba)?;
+
+        self.read_block(pi, &loc, data)
+    }
+
+    pub fn write_lba(
+        &mut self,
+        pi: &mut Pi,
+        system: &SystemArea,
+        lba: u32,
+        data: &[u8],
+    ) -> Result<(), DdError> {
+        if !system.is_writable(lba) {
+            return Err(DdError::WriteProtected);
+        }
+
+        let loc = system.locate(lba)?;
+
+        self.write_block(pi, &loc, data)
+    }
+
+    fn read_bcd_pair(&mut self, pi: &mut Pi, cmd: u16) -> Result<(u8, u8), DdError> {
+        let [hi, lo] = self.command(pi, cmd, 0)?.to_be_bytes();
+
+        match (from_bcd(hi), from_bcd(lo)) {
+            (Some(hi), Some(lo)) => Ok((hi, lo)),
+            _ => Err(DdError::InvalidTime),
+        }
+    }
+
+    /// the drive's clock only keeps two digits of the year; like libleo, 96-99 are the 1900s
+    pub fn read_rtc(&mut self, pi: &mut Pi) -> Result<DateTime, DdError> {
+        let (year, month) = self.read_bcd_pair(pi, CMD_GET_YEAR_MONTH)?;
+        let (day, hour) = self.read_bcd_pair(pi, CMD_GET_DAY_HOUR)?;
+        let (minute, second) = self.read_bcd_pair(pi, CMD_GET_MINUTE_SECOND)?;
+
+        let year = if year >= 96 { 1900 } else { 2000 } + year as u16;
+
+        if !(1..=12).contains(&month) {
+            return Err(DdError::InvalidTime);
+        }
+
+        let mut time = DateTime {
+            year,
+            month,
+            day,
+            weekday: 0,
+            hour,
+            minute,
+            second,
+        };
+
+        time.weekday = weekday(&time);
+
+        if time.is_valid() {
+            Ok(time)
+        } else {
+            Err(DdError::InvalidTime)
+        }
+    }
+
+    pub fn set_rtc(&mut self, pi: &mut Pi, time: &DateTime) -> Result<(), DdError> {
+        if !time.is_valid() || !(1996..2096).contains(&time.year) {
+            return Err(DdError::InvalidTime);
+        }
+
+        let pair = |hi: u8, lo: u8| u16::from_be_bytes([to_bcd(hi), to_bcd(lo)]);
+
+        self.command(
+            pi,
+            CMD_SET_YEAR_MONTH,
+            pair((time.year % 100) as u8, time.month),
+        )?;
+        self.command(pi, CMD_SET_DAY_HOUR, pair(time.day, time.hour))?;
+        self.command(pi, CMD_SET_MINUTE_SECOND, pair(time.minute, time.second))?;
+
+        Ok(())
+    }
+}
+
+// the drive doesn't keep the day of the week, so work it out; 0 is Sunday
+fn weekday(time: &DateTime) -> u8 {
+    const OFFSETS: [u16; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+
+    let year = time.year - (time.month < 3) as u16;
+    let days = year + year / 4 - year / 100 + year / 400;
+
+    ((days + OFFSETS[time.month as usize - 1] + time.day as u16) % 7) as u8
+}
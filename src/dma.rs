@@ -0,0 +1,257 @@
+use core::mem::size_of_val;
+
+use crate::boot::interrupts::im;
+use crate::cop0::cop0;
+use crate::pi::{pi, Pi};
+use crate::types::Align8;
+use crate::util::{k0_to_phys, k0_to_phys_mut};
+use crate::{data_cache_invalidate, data_cache_invalidate_raw, data_cache_writeback};
+
+const QUEUE_LEN: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    /// cartridge to RAM
+    Read,
+    /// RAM to cartridge
+    Write,
+}
+
+#[derive(Clone, Copy)]
+struct Request {
+    direction: Direction,
+    buf: usize,
+    dram: usize,
+    cart: u32,
+    len: usize,
+    sequence: u32,
+    on_complete: Option<fn(DmaHandle)>,
+}
+
+impl Request {
+    const EMPTY: Self = Self {
+        direction: Direction::Read,
+        buf: 0,
+        dram: 0,
+        cart: 0,
+        len: 0,
+        sequence: 0,
+        on_complete: None,
+    };
+}
+
+/// identifies a transfer queued with `Pi::queue_read_into` or `Pi::queue_write`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DmaHandle(u32);
+
+struct DmaQueue {
+    requests: [Request; QUEUE_LEN],
+    head: usize,
+    len: usize,
+    // whether the request at `head` is on the bus right now
+    active: bool,
+    sequence: u32,
+    completed: u32,
+}
+
+static mut QUEUE: DmaQueue = DmaQueue {
+    requests: [Request::EMPTY; QUEUE_LEN],
+    head: 0,
+    len: 0,
+    active: false,
+    sequence: 0,
+    completed: 0,
+};
+
+fn queue() -> &'static mut DmaQueue {
+    unsafe { &mut *(&raw mut QUEUE) }
+}
+
+fn pi_interrupt() -> bool {
+    pi().handle_dma_interrupt();
+    // already acknowledged, and acking again could lose the interrupt for the next request
+    false
+}
+
+impl Pi {
+    /// installs the PI interrupt handler which drives queued transfers
+    pub fn enable_dma_queue(&mut self) {
+        let im = im();
+        im.set_pi_fn(Some(pi_interrupt));
+        im.set_pi(true);
+    }
+
+    // updated from the interrupt handler, so it mustn't be cached in a register
+    fn queue_len(&self) -> usize {
+        unsafe { (&raw const QUEUE.len).read_volatile() }
+    }
+
+    pub fn is_queue_busy(&self) -> bool {
+        self.queue_len() != 0
+    }
+
+    /// waits for every queued transfer to finish, so the bus is free for a blocking one
+    pub fn wait_queue(&self) {
+        while self.is_queue_busy() {}
+    }
+
+    pub fn is_done(&self, handle: DmaHandle) -> bool {
+        let completed = unsafe { (&raw const QUEUE.completed).read_volatile() };
+
+        completed.wrapping_sub(handle.0) as i32 >= 0
+    }
+
+    pub fn wait_done(&self, handle: DmaHandle) {
+        while !self.is_done(handle) {}
+    }
+
+    fn start_request(&mut self, request: &Request) {
+        self.set_dram_addr(request.dram as u32);
+        self.set_cart_addr(request.cart);
+
+        match request.direction {
+            Direction::Read => self.set_wr_len((request.len - 1) as _),
+            Direction::Write => self.set_rd_len((request.len - 1) as _),
+        }
+    }
+
+    fn enqueue(&mut self, request: Request) -> Option<DmaHandle> {
+        let cop0 = cop0();
+
+        cop0.disable_interrupts();
+
+        let queue = queue();
+
+        if queue.len == QUEUE_LEN {
+            cop0.enable_interrupts();
+            return None;
+        }
+
+        queue.sequence = queue.sequence.wrapping_add(1);
+
+        let request = Request {
+            sequence: queue.sequence,
+            ..request
+        };
+
+        queue.requests[(queue.head + queue.len) % QUEUE_LEN] = request;
+        queue.len += 1;
+
+        // otherwise the interrupt for the one in flight starts it
+        if !queue.active {
+            self.wait();
+
+            queue.active = true;
+            self.start_request(&request);
+        }
+
+        cop0.enable_interrupts();
+
+        Some(DmaHandle(request.sequence))
+    }
+
+    #[track_caller]
+    fn check_transfer(addr: u32, len: usize) {
+        assert!(
+            addr % 2 == 0,
+            "PI address ({addr:08X}) must be 2-byte aligned, otherwise behaviour is not well-defined"
+        );
+        assert!(
+            len % 2 == 0,
+            "Length ({len:X}) must be a multiple of 2, otherwise behaviour is not well-defined"
+        );
+    }
+
+    /// queues a transfer from the cartridge into `data`, returning `None` if the queue is full
+    ///
+    /// `enable_dma_queue` must have been called, otherwise only the first transfer ever finishes
+    ///
+    /// # Safety
+    ///
+    /// `data` mustn't be touched, or go out of scope, until the transfer is done
+    #[track_caller]
+    pub unsafe fn queue_read_into<T>(
+        &mut self,
+        data: &mut Align8<[T]>,
+        addr: u32,
+        on_complete: Option<fn(DmaHandle)>,
+    ) -> Option<DmaHandle> {
+        let len = size_of_val(&data.0);
+
+        Self::check_transfer(addr, len);
+
+        data_cache_invalidate(&data.0);
+
+        self.enqueue(Request {
+            direction: Direction::Read,
+            buf: data.0.as_ptr().addr(),
+            dram: k0_to_phys_mut(data.0.as_mut_ptr()).addr(),
+            cart: addr,
+            len,
+            sequence: 0,
+            on_complete,
+        })
+    }
+
+    /// queues a transfer from `data` to the cartridge, returning `None` if the queue is full
+    ///
+    /// # Safety
+    ///
+    /// `data` mustn't be modified, or go out of scope, until the transfer is done
+    #[track_caller]
+    pub unsafe fn queue_write<T>(
+        &mut self,
+        data: &Align8<[T]>,
+        addr: u32,
+        on_complete: Option<fn(DmaHandle)>,
+    ) -> Option<DmaHandle> {
+        let len = size_of_val(&data.0);
+
+        Self::check_transfer(addr, len);
+
+        data_cache_writeback(&data.0);
+
+        self.enqueue(Request {
+            direction: Direction::Write,
+            buf: data.0.as_ptr().addr(),
+            dram: k0_to_phys(data.0.as_ptr()).addr(),
+            cart: addr,
+            len,
+            sequence: 0,
+            on_complete,
+        })
+    }
+
+    fn handle_dma_interrupt(&mut self) {
+        // ack before starting the next DMA, so its completion can't be lost
+        self.set_status(1 << 1);
+
+        let queue = queue();
+
+        // blocking transfers interrupt too, if the interrupt is enabled
+        if !queue.active {
+            return;
+        }
+
+        let done = queue.requests[queue.head];
+
+        if done.direction == Direction::Read {
+            // in case anything pulled the buffer back into the cache while the DMA was running
+            data_cache_invalidate_raw(done.buf, done.buf + done.len);
+        }
+
+        queue.head = (queue.head + 1) % QUEUE_LEN;
+        queue.len -= 1;
+        queue.completed = done.sequence;
+
+        if queue.len == 0 {
+            queue.active = false;
+        } else {
+            self.start_request(&queue.requests[queue.head]);
+        }
+
+        if let Some(on_complete) = done.on_complete {
+            on_complete(DmaHandle(done.sequence));
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use crate::cop0::cop0;
+use crate::joybus::{parse_single_response, JoybusCommand, EEPROM_CHANNEL};
+use crate::si::Si;
+
+pub const BLOCK_SIZE: usize = 8;
+
+// the EEPROM ignores commands while it's busy programming a block
+const WRITE_DELAY_US: u32 = 15_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EepromType {
+    Eeprom4K,
+    Eeprom16K,
+}
+
+impl EepromType {
+    pub const fn blocks(self) -> usize {
+        match self {
+            Self::Eeprom4K => 64,
+            Self::Eeprom16K => 256,
+        }
+    }
+
+    pub const fn size(self) -> usize {
+        self.blocks() * BLOCK_SIZE
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EepromError {
+    NotPresent,
+    OutOfRange,
+    Transfer,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Eeprom {
+    eeprom_type: EepromType,
+}
+
+impl Eeprom {
+    pub fn detect(si: &mut Si) -> Option<Self> {
+        let cmd = JoybusCommand::Info;
+
+        let response = si.transact_single(EEPROM_CHANNEL, cmd);
+
+        let Ok(Some(data)) = parse_single_response(cmd, &response, EEPROM_CHANNEL) else {
+            return None;
+        };
+
+        let eeprom_type = match u16::from_be_bytes([data[0], data[1]]) {
+            0x0080 => EepromType::Eeprom4K,
+            0x00C0 => EepromType::Eeprom16K,
+            _ => return None,
+        };
+
+        Some(Self { eeprom_type })
+    }
+
+    pub fn eeprom_type(&self) -> EepromType {
+        self.eeprom_type
+    }
+
+    fn check_range(&self, block: usize, count: usize) -> Result<(), EepromError> {
+        if block + count > self.eeprom_type.blocks() {
+            Err(EepromError::OutOfRange)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn read_block(
+        &self,
+        si: &mut Si,
+        block: usize,
+        buf: &mut [u8; BLOCK_SIZE],
+    ) -> Result<(), EepromError> {
+        self.check_range(block, 1)?;
+
+        let cmd = JoybusCommand::EepromRead(block as u8);
+
+        let response = si.transact_single(EEPROM_CHANNEL, cmd);
+
+        match parse_single_response(cmd, &response, EEPROM_CHANNEL) {
+            Ok(Some(data)) => {
+                buf.copy_from_slice(data);
+                Ok(())
+            }
+            Ok(None) => Err(EepromError::NotPresent),
+            Err(()) => Err(EepromError::Transfer),
+        }
+    }
+
+    /// writes a single block; the EEPROM will be busy for a while afterwards, so the caller is
+    /// responsible for waiting before the next write (`write_range` does this automatically)
+    pub fn write_block(
+        &self,
+        si: &mut Si,
+        block: usize,
+        data: &[u8; BLOCK_SIZE],
+    ) -> Result<(), EepromError> {
+        self.check_range(block, 1)?;
+
+        let cmd = JoybusCommand::EepromWrite(block as u8, *data);
+
+        let response = si.transact_single(EEPROM_CHANNEL, cmd);
+
+        match parse_single_response(cmd, &response, EEPROM_CHANNEL) {
+            Ok(Some(_)) => Ok(()),
+            Ok(None) => Err(EepromError::NotPresent),
+            Err(()) => Err(EepromError::Transfer),
+        }
+    }
+
+    /// reads as many blocks as fit in `buf`, starting from block 0
+    #[track_caller]
+    pub fn read_all(&self, si: &mut Si, buf: &mut [u8]) -> Result<(), EepromError> {
+        let len = buf.len();
+
+        assert!(
+            len % BLOCK_SIZE == 0,
+            "Length ({len:X}) must be a multiple of the block size"
+        );
+
+        self.check_range(0, len / BLOCK_SIZE)?;
+
+        for (block, chunk) in buf.chunks_exact_mut(BLOCK_SIZE).enumerate() {
+            self.read_block(si, block, chunk.try_into().unwrap())?;
+        }
+
+        Ok(())
+    }
+
+    #[track_caller]
+    pub fn write_range(
+        &self,
+        si: &mut Si,
+        start_block: usize,
+        data: &[u8],
+    ) -> Result<(), EepromError> {
+        let len = data.len();
+
+        assert!(
+            len % BLOCK_SIZE == 0,
+            "Length ({len:X}) must be a multiple of the block size"
+        );
+
+        self.check_range(start_block, len / BLOCK_SIZE)?;
+
+        let cop0 = cop0();
+
+        for (index, chunk) in data.chunks_exact(BLOCK_SIZE).enumerate() {
+            if index != 0 {
+                cop0.delay(WRITE_DELAY_US);
+            }
+
+            self.write_block(si, start_block + index, chunk.try_into().unwrap())?;
+        }
+
+        cop0.delay(WRITE_DELAY_US);
+
+        Ok(())
+    }
+}
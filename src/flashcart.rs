@@ -0,0 +1,691 @@
+use core::fmt;
+use core::ptr::from_raw_parts_mut;
+
+use crate::boot::ms_to_ticks;
+use crate::cop0::cop0;
+use crate::io_ptr;
+use crate::pi::Pi;
+use crate::rom::ROM_BASE;
+use crate::types::Align8;
+
+/// the most a packet to the host can carry, and the most of one from it the cart holds at a time
+pub const MAX_PACKET: usize = 0x1000;
+
+// DMAs go through here, since callers' buffers needn't be aligned
+const CHUNK_SIZE: usize = 0x200;
+
+const TIMEOUT_MS: u32 = 1_000;
+
+/// what a packet holds, as UNFLoader numbers them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataType {
+    Text,
+    Binary,
+    Header,
+    Screenshot,
+    Heartbeat,
+    Other(u8),
+}
+
+impl DataType {
+    pub fn from_u8(val: u8) -> Self {
+        match val {
+            0x01 => Self::Text,
+            0x02 => Self::Binary,
+            0x03 => Self::Header,
+            0x04 => Self::Screenshot,
+            0x05 => Self::Heartbeat,
+            _ => Self::Other(val),
+        }
+    }
+
+    pub fn as_u8(self) -> u8 {
+        match self {
+            Self::Text => 0x01,
+            Self::Binary => 0x02,
+            Self::Header => 0x03,
+            Self::Screenshot => 0x04,
+            Self::Heartbeat => 0x05,
+            Self::Other(val) => val,
+        }
+    }
+
+    // the type and length share a word in every cart's header
+    fn header(self, len: usize) -> u32 {
+        ((self.as_u8() as u32) << 24) | (len as u32 & 0x00FF_FFFF)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlashcartError {
+    PacketTooLarge,
+    /// the cart was still busy when the timeout ran out
+    Timeout,
+    /// the cart rejected a command
+    Command,
+    /// what came from the host wasn't a packet
+    Framing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Packet {
+    pub data_type: DataType,
+    pub len: usize,
+}
+
+/// a flashcart's USB port, as a way to exchange packets with the host
+pub trait FlashcartLink {
+    /// blocks until the cart has taken the whole packet
+    fn send(&mut self, pi: &mut Pi, data_type: DataType, data: &[u8])
+        -> Result<(), FlashcartError>;
+
+    /// checks for a packet from the host, filling `buf` with it if there is one; a packet larger
+    /// than `buf` is truncated, but all of it is still taken from the cart
+    fn poll(&mut self, pi: &mut Pi, buf: &mut [u8]) -> Result<Option<Packet>, FlashcartError>;
+}
+
+fn read_reg(pi: &mut Pi, reg: *mut u32) -> u32 {
+    pi.wait();
+
+    unsafe { reg.read_volatile() }
+}
+
+fn write_reg(pi: &mut Pi, reg: *mut u32, val: u32) {
+    pi.wait();
+
+    unsafe { reg.write_volatile(val) }
+}
+
+/// spins until `done` returns true
+fn wait_until(mut done: impl FnMut() -> bool) -> Result<(), FlashcartError> {
+    let cop0 = cop0();
+
+    let start = cop0.count();
+    let timeout = ms_to_ticks(TIMEOUT_MS);
+
+    while !done() {
+        if cop0.count().wrapping_sub(start) >= timeout {
+            return Err(FlashcartError::Timeout);
+        }
+    }
+
+    Ok(())
+}
+
+fn bounce(buf: &mut Align8<[u8; CHUNK_SIZE]>, len: usize) -> &mut Align8<[u8]> {
+    unsafe { &mut *from_raw_parts_mut::<Align8<[u8]>>(buf.0.as_mut_ptr() as *mut (), len) }
+}
+
+/// copies `data` out to the cart at `addr`, padding it to a whole number of halfwords
+fn copy_to_cart(pi: &mut Pi, addr: u32, data: &[u8]) {
+    let mut buf = Align8([0; CHUNK_SIZE]);
+
+    for (i, chunk) in data.chunks(CHUNK_SIZE).enumerate() {
+        let len = chunk.len().next_multiple_of(2);
+
+        buf.0[..chunk.len()].copy_from_slice(chunk);
+        buf.0[chunk.len()..len].fill(0);
+
+        pi.write(bounce(&mut buf, len), addr + (i * CHUNK_SIZE) as u32);
+    }
+}
+
+fn copy_from_cart(pi: &mut Pi, addr: u32, data: &mut [u8]) {
+    let mut buf = Align8([0; CHUNK_SIZE]);
+
+    for (i, chunk) in data.chunks_mut(CHUNK_SIZE).enumerate() {
+        let len = chunk.len().next_multiple_of(2);
+
+        pi.read_into(bounce(&mut buf, len), addr + (i * CHUNK_SIZE) as u32);
+
+        chunk.copy_from_slice(&buf.0[..chunk.len()]);
+    }
+}
+
+const D64_BASE: u32 = 0x1800_0000;
+
+const D64_STATUS: *mut u32 = io_ptr!(mut D64_BASE + 0x0200);
+const D64_COMMAND: *mut u32 = io_ptr!(mut D64_BASE + 0x0208);
+const D64_MAGIC: *mut u32 = io_ptr!(mut D64_BASE + 0x02EC);
+const D64_USB_COMSTAT: *mut u32 = io_ptr!(mut D64_BASE + 0x0400);
+const D64_USB_P0: *mut u32 = io_ptr!(mut D64_BASE + 0x0404);
+const D64_USB_P1: *mut u32 = io_ptr!(mut D64_BASE + 0x0408);
+
+// "UDEV"
+const D64_MAGIC_VALUE: u32 = 0x5544_4556;
+
+const D64_STATUS_BUSY: u32 = 0x1000;
+
+const D64_CMD_ENABLE_ROM_WRITE: u32 = 0xF0;
+const D64_CMD_DISABLE_ROM_WRITE: u32 = 0xF1;
+
+const D64_USB_WRITE: u32 = 0x08;
+const D64_USB_ARM: u32 = 0x0A;
+const D64_USB_DISARM: u32 = 0x0F;
+
+const D64_USB_WRITE_BUSY: u32 = 0x0F << 4;
+const D64_USB_ARM_STATE: u32 = 0x0F << 8;
+const D64_USB_ARM_IDLE: u32 = 0x00 << 8;
+const D64_USB_UNARMED_DATA: u32 = 0x03 << 8;
+
+// at the end of SDRAM, which the ROM mustn't reach
+const D64_TX_BUFFER: u32 = 0x03FE_0000;
+const D64_RX_BUFFER: u32 = D64_TX_BUFFER + MAX_PACKET as u32;
+
+/// the 64drive's USB port, which moves packets in and out of the cart's SDRAM
+pub struct SixtyFourDrive {
+    _private: (),
+}
+
+impl SixtyFourDrive {
+    pub fn detect(pi: &mut Pi) -> Option<Self> {
+        if read_reg(pi, D64_MAGIC) != D64_MAGIC_VALUE {
+            return None;
+        }
+
+        Some(Self { _private: () })
+    }
+
+    fn command(pi: &mut Pi, cmd: u32) -> Result<(), FlashcartError> {
+        wait_until(|| read_reg(pi, D64_STATUS) & D64_STATUS_BUSY == 0)?;
+
+        write_reg(pi, D64_COMMAND, cmd);
+
+        wait_until(|| read_reg(pi, D64_STATUS) & D64_STATUS_BUSY == 0)
+    }
+
+    fn usb_command(pi: &mut Pi, cmd: u32, addr: u32, param: u32) {
+        write_reg(pi, D64_USB_P0, addr >> 1);
+        write_reg(pi, D64_USB_P1, param);
+        write_reg(pi, D64_USB_COMSTAT, cmd);
+    }
+}
+
+impl FlashcartLink for SixtyFourDrive {
+    fn send(
+        &mut self,
+        pi: &mut Pi,
+        data_type: DataType,
+        data: &[u8],
+    ) -> Result<(), FlashcartError> {
+        if data.len() > MAX_PACKET {
+            return Err(FlashcartError::PacketTooLarge);
+        }
+
+        wait_until(|| read_reg(pi, D64_USB_COMSTAT) & D64_USB_WRITE_BUSY == 0)?;
+
+        Self::command(pi, D64_CMD_ENABLE_ROM_WRITE)?;
+        copy_to_cart(pi, ROM_BASE + D64_TX_BUFFER, data);
+        Self::command(pi, D64_CMD_DISABLE_ROM_WRITE)?;
+
+        Self::usb_command(
+            pi,
+            D64_USB_WRITE,
+            D64_TX_BUFFER,
+            data_type.header(data.len()),
+        );
+
+        wait_until(|| read_reg(pi, D64_USB_COMSTAT) & D64_USB_WRITE_BUSY == 0)
+    }
+
+    fn poll(&mut self, pi: &mut Pi, buf: &mut [u8]) -> Result<Option<Packet>, FlashcartError> {
+        let state = read_reg(pi, D64_USB_COMSTAT) & D64_USB_ARM_STATE;
+
+        if state == D64_USB_ARM_IDLE {
+            Self::usb_command(pi, D64_USB_ARM, D64_RX_BUFFER, MAX_PACKET as u32);
+            return Ok(None);
+        }
+
+        // armed the last time round, and still waiting
+        if state != D64_USB_UNARMED_DATA {
+            return Ok(None);
+        }
+
+        let header = read_reg(pi, D64_USB_P0);
+        let len = (header & 0x00FF_FFFF) as usize;
+
+        let copied = len.min(buf.len());
+
+        // only `MAX_PACKET` was armed for, so the rest of a larger packet is still in the cart's
+        // FIFO and has to be taken in pieces, or it's mistaken for the next packet
+        let mut offset = 0;
+
+        loop {
+            let chunk = (len - offset).min(MAX_PACKET);
+
+            if offset < copied {
+                let end = copied.min(offset + chunk);
+                copy_from_cart(pi, ROM_BASE + D64_RX_BUFFER, &mut buf[offset..end]);
+            }
+
+            Self::usb_command(pi, D64_USB_DISARM, 0, 0);
+
+            offset += chunk;
+
+            if offset >= len {
+                break;
+            }
+
+            let next = (len - offset).min(MAX_PACKET);
+            Self::usb_command(pi, D64_USB_ARM, D64_RX_BUFFER, next as u32);
+
+            wait_until(|| {
+                read_reg(pi, D64_USB_COMSTAT) & D64_USB_ARM_STATE == D64_USB_UNARMED_DATA
+            })?;
+        }
+
+        Ok(Some(Packet {
+            data_type: DataType::from_u8((header >> 24) as u8),
+            len: copied,
+        }))
+    }
+}
+
+const ED_BASE: u32 = 0x1F80_0000;
+
+const ED_USB_CFG: *mut u32 = io_ptr!(mut ED_BASE + 0x0004);
+const ED_VERSION: *mut u32 = io_ptr!(mut ED_BASE + 0x0014);
+const ED_USB_DATA: u32 = ED_BASE + 0x0400;
+const ED_KEY: *mut u32 = io_ptr!(mut ED_BASE + 0x8004);
+
+// unlocks the registers
+const ED_KEY_VALUE: u32 = 0xAA55;
+
+const ED_VERSION_V3: u32 = 0xED64_0008;
+const ED_VERSION_X7: u32 = 0xED64_0013;
+
+const ED_USB_MODE_READ: u32 = 0xC600;
+const ED_USB_MODE_WRITE: u32 = 0xC200;
+
+const ED_USB_ACTIVE: u32 = 0x0200;
+// set while the receive FIFO is empty
+const ED_USB_RX_EMPTY: u32 = 0x0400;
+const ED_USB_POWER: u32 = 0x1000;
+
+// transfers fill the FIFO's buffer from the end, so that they always finish at its last byte
+const ED_FIFO_SIZE: usize = 0x200;
+
+// the EverDrive just passes bytes through, so packets need framing of their own
+const ED_HEADER: [u8; 4] = *b"DMA@";
+const ED_TRAILER: [u8; 4] = *b"CMPH";
+
+/// the EverDrive 64's USB FIFO, on the X7 and the v3
+pub struct EverDrive {
+    _private: (),
+}
+
+impl EverDrive {
+    pub fn detect(pi: &mut Pi) -> Option<Self> {
+        write_reg(pi, ED_KEY, ED_KEY_VALUE);
+
+        match read_reg(pi, ED_VERSION) {
+            ED_VERSION_V3 | ED_VERSION_X7 => Some(Self { _private: () }),
+            _ => None,
+        }
+    }
+
+    fn wait_idle(pi: &mut Pi) -> Result<(), FlashcartError> {
+        wait_until(|| read_reg(pi, ED_USB_CFG) & ED_USB_ACTIVE == 0)
+    }
+
+    /// writes `data` to the FIFO, which has to be an even length
+    fn write_fifo(pi: &mut Pi, data: &[u8]) -> Result<(), FlashcartError> {
+        let mut buf = Align8([0; CHUNK_SIZE]);
+
+        for chunk in data.chunks(ED_FIFO_SIZE) {
+            let offset = (ED_FIFO_SIZE - chunk.len()) as u32;
+
+            Self::wait_idle(pi)?;
+
+            buf.0[..chunk.len()].copy_from_slice(chunk);
+            pi.write(bounce(&mut buf, chunk.len()), ED_USB_DATA + offset);
+
+            write_reg(pi, ED_USB_CFG, ED_USB_MODE_WRITE | offset);
+        }
+
+        Self::wait_idle(pi)
+    }
+
+    /// fills `data` from the FIFO, which has to be an even length
+    fn read_fifo(pi: &mut Pi, data: &mut [u8]) -> Result<(), FlashcartError> {
+        let mut buf = Align8([0; CHUNK_SIZE]);
+
+        for chunk in data.chunks_mut(ED_FIFO_SIZE) {
+            let offset = (ED_FIFO_SIZE - chunk.len()) as u32;
+
+            write_reg(pi, ED_USB_CFG, ED_USB_MODE_READ | offset);
+
+            Self::wait_idle(pi)?;
+
+            pi.read_into(bounce(&mut buf, chunk.len()), ED_USB_DATA + offset);
+            chunk.copy_from_slice(&buf.0[..chunk.len()]);
+        }
+
+        Ok(())
+    }
+}
+
+impl FlashcartLink for EverDrive {
+    fn send(
+        &mut self,
+        pi: &mut Pi,
+        data_type: DataType,
+        data: &[u8],
+    ) -> Result<(), FlashcartError> {
+        if data.len() > MAX_PACKET {
+            return Err(FlashcartError::PacketTooLarge);
+        }
+
+        let mut header = [0; 8];
+        header[..4].copy_from_slice(&ED_HEADER);
+        header[4..].copy_from_slice(&data_type.header(data.len()).to_be_bytes());
+
+        Self::write_fifo(pi, &header)?;
+
+        // the payload is padded to a halfword, like UNFLoader expects
+        let (whole, rest) = data.split_at(data.len() & !1);
+        Self::write_fifo(pi, whole)?;
+
+        let mut trailer = [0; 6];
+        let trailer = match rest {
+            [byte] => {
+                trailer[0] = *byte;
+                trailer[2..].copy_from_slice(&ED_TRAILER);
+                &trailer[..]
+            }
+            _ => &ED_TRAILER[..],
+        };
+
+        Self::write_fifo(pi, trailer)
+    }
+
+    fn poll(&mut self, pi: &mut Pi, buf: &mut [u8]) -> Result<Option<Packet>, FlashcartError> {
+        if read_reg(pi, ED_USB_CFG) & (ED_USB_POWER | ED_USB_RX_EMPTY) != ED_USB_POWER {
+            return Ok(None);
+        }
+
+        let mut header = [0; 8];
+        Self::read_fifo(pi, &mut header)?;
+
+        if header[..4] != ED_HEADER {
+            return Err(FlashcartError::Framing);
+        }
+
+        let word = u32::from_be_bytes(header[4..].try_into().unwrap());
+        let len = (word & 0x00FF_FFFF) as usize;
+
+        let copied = len.min(buf.len());
+
+        // straight into `buf` as far as whole halfwords go, then the rest a halfword at a time,
+        // keeping what fits; everything, padding included, has to come out of the FIFO
+        let direct = copied & !1;
+        Self::read_fifo(pi, &mut buf[..direct])?;
+
+        let mut halfword = [0; 2];
+        for pos in (direct..len.next_multiple_of(2)).step_by(2) {
+            Self::read_fifo(pi, &mut halfword)?;
+
+            for (i, &byte) in halfword.iter().enumerate() {
+                if pos + i < copied {
+                    buf[pos + i] = byte;
+                }
+            }
+        }
+
+        let mut trailer = [0; 4];
+        Self::read_fifo(pi, &mut trailer)?;
+
+        if trailer != ED_TRAILER {
+            return Err(FlashcartError::Framing);
+        }
+
+        Ok(Some(Packet {
+            data_type: DataType::from_u8((word >> 24) as u8),
+            len: copied,
+        }))
+    }
+}
+
+const SC64_BASE: u32 = 0x1FFF_0000;
+
+const SC64_SCR: *mut u32 = io_ptr!(mut SC64_BASE);
+const SC64_DATA0: *mut u32 = io_ptr!(mut SC64_BASE + 0x04);
+const SC64_DATA1: *mut u32 = io_ptr!(mut SC64_BASE + 0x08);
+const SC64_IDENTIFIER: *mut u32 = io_ptr!(mut SC64_BASE + 0x0C);
+const SC64_KEY: *mut u32 = io_ptr!(mut SC64_BASE + 0x10);
+
+// "SCv2"
+const SC64_IDENTIFIER_VALUE: u32 = 0x5343_7632;
+// "_UNLOCK_", after a zero to reset the sequence
+const SC64_UNLOCK: [u32; 3] = [0, 0x5F55_4E4C, 0x4F43_4B5F];
+
+const SC64_SCR_BUSY: u32 = 0x8000_0000;
+const SC64_SCR_ERROR: u32 = 0x4000_0000;
+
+const SC64_CMD_USB_WRITE_STATUS: u8 = b'U';
+const SC64_CMD_USB_WRITE: u8 = b'M';
+const SC64_CMD_USB_READ_STATUS: u8 = b'u';
+const SC64_CMD_USB_READ: u8 = b'm';
+
+const SC64_USB_BUSY: u32 = 0x8000_0000;
+
+// the cart's own scratch buffer, so the ROM in SDRAM is left alone
+const SC64_TX_BUFFER: u32 = 0x1FFE_0000;
+const SC64_RX_BUFFER: u32 = SC64_TX_BUFFER + MAX_PACKET as u32;
+
+/// the SummerCart64's USB port, driven through its command interface
+pub struct SummerCart64 {
+    _private: (),
+}
+
+impl SummerCart64 {
+    pub fn detect(pi: &mut Pi) -> Option<Self> {
+        for key in SC64_UNLOCK {
+            write_reg(pi, SC64_KEY, key);
+        }
+
+        if read_reg(pi, SC64_IDENTIFIER) != SC64_IDENTIFIER_VALUE {
+            return None;
+        }
+
+        Some(Self { _private: () })
+    }
+
+    fn command(pi: &mut Pi, cmd: u8, args: [u32; 2]) -> Result<[u32; 2], FlashcartError> {
+        write_reg(pi, SC64_DATA0, args[0]);
+        write_reg(pi, SC64_DATA1, args[1]);
+        write_reg(pi, SC64_SCR, cmd as u32);
+
+        wait_until(|| read_reg(pi, SC64_SCR) & SC64_SCR_BUSY == 0)?;
+
+        if read_reg(pi, SC64_SCR) & SC64_SCR_ERROR != 0 {
+            return Err(FlashcartError::Command);
+        }
+
+        Ok([read_reg(pi, SC64_DATA0), read_reg(pi, SC64_DATA1)])
+    }
+
+    fn wait_usb(pi: &mut Pi, status_cmd: u8) -> Result<[u32; 2], FlashcartError> {
+        let mut result = Ok([0; 2]);
+
+        wait_until(|| {
+            result = Self::command(pi, status_cmd, [0; 2]);
+            !matches!(result, Ok([status, _]) if status & SC64_USB_BUSY != 0)
+        })?;
+
+        result
+    }
+}
+
+impl FlashcartLink for SummerCart64 {
+    fn send(
+        &mut self,
+        pi: &mut Pi,
+        data_type: DataType,
+        data: &[u8],
+    ) -> Result<(), FlashcartError> {
+        if data.len() > MAX_PACKET {
+            return Err(FlashcartError::PacketTooLarge);
+        }
+
+        Self::wait_usb(pi, SC64_CMD_USB_WRITE_STATUS)?;
+
+        copy_to_cart(pi, SC64_TX_BUFFER, data);
+
+        Self::command(
+            pi,
+            SC64_CMD_USB_WRITE,
+            [SC64_TX_BUFFER, data_type.header(data.len())],
+        )?;
+
+        Self::wait_usb(pi, SC64_CMD_USB_WRITE_STATUS).map(|_| ())
+    }
+
+    fn poll(&mut self, pi: &mut Pi, buf: &mut [u8]) -> Result<Option<Packet>, FlashcartError> {
+        let [status, len] = Self::command(pi, SC64_CMD_USB_READ_STATUS, [0; 2])?;
+
+        if status & SC64_USB_BUSY != 0 || len == 0 {
+            return Ok(None);
+        }
+
+        let len = len as usize;
+
+        let copied = len.min(buf.len());
+
+        // the whole packet has to be taken, even if only part of it fits, and the buffer only
+        // holds `MAX_PACKET` at a time; anything left behind is mistaken for the next packet
+        for offset in (0..len).step_by(MAX_PACKET) {
+            let chunk = (len - offset).min(MAX_PACKET);
+
+            Self::command(pi, SC64_CMD_USB_READ, [SC64_RX_BUFFER, chunk as u32])?;
+            Self::wait_usb(pi, SC64_CMD_USB_READ_STATUS)?;
+
+            if offset < copied {
+                let end = copied.min(offset + chunk);
+                copy_from_cart(pi, SC64_RX_BUFFER, &mut buf[offset..end]);
+            }
+        }
+
+        Ok(Some(Packet {
+            data_type: DataType::from_u8(status as u8),
+            len: copied,
+        }))
+    }
+}
+
+/// whichever flashcart the console was booted from
+pub enum Flashcart {
+    SixtyFourDrive(SixtyFourDrive),
+    EverDrive(EverDrive),
+    SummerCart64(SummerCart64),
+}
+
+impl Flashcart {
+    /// probes each cart's registers in turn; the 64drive goes first since probing the others'
+    /// unlock registers is harmless on it
+    pub fn detect(pi: &mut Pi) -> Option<Self> {
+        if let Some(cart) = SixtyFourDrive::detect(pi) {
+            return Some(Self::SixtyFourDrive(cart));
+        }
+
+        if let Some(cart) = SummerCart64::detect(pi) {
+            return Some(Self::SummerCart64(cart));
+        }
+
+        EverDrive::detect(pi).map(Self::EverDrive)
+    }
+
+    fn link(&mut self) -> &mut dyn FlashcartLink {
+        match self {
+            Self::SixtyFourDrive(cart) => cart,
+            Self::EverDrive(cart) => cart,
+            Self::SummerCart64(cart) => cart,
+        }
+    }
+}
+
+impl FlashcartLink for Flashcart {
+    fn send(
+        &mut self,
+        pi: &mut Pi,
+        data_type: DataType,
+        data: &[u8],
+    ) -> Result<(), FlashcartError> {
+        self.link().send(pi, data_type, data)
+    }
+
+    fn poll(&mut self, pi: &mut Pi, buf: &mut [u8]) -> Result<Option<Packet>, FlashcartError> {
+        self.link().poll(pi, buf)
+    }
+}
+
+static mut FLASHCART: Option<Flashcart> = None;
+
+/// looks for a flashcart, which `flashcart` then returns if one was found
+pub fn init(pi: &mut Pi) {
+    unsafe { FLASHCART = Flashcart::detect(pi) }
+}
+
+#[allow(static_mut_refs)]
+pub fn flashcart() -> Option<&'static mut Flashcart> {
+    unsafe { FLASHCART.as_mut() }
+}
+
+// enough for a log line, so each one goes out as a single packet
+const WRITER_BUFFER: usize = 0x100;
+
+/// text output over a flashcart's USB port, gathered into packets and sent when the buffer fills
+/// or the writer is dropped
+pub struct FlashcartWriter<'a> {
+    cart: &'a mut Flashcart,
+    pi: &'a mut Pi,
+    buf: [u8; WRITER_BUFFER],
+    len: usize,
+}
+
+impl<'a> FlashcartWriter<'a> {
+    pub fn new(cart: &'a mut Flashcart, pi: &'a mut Pi) -> Self {
+        Self {
+            cart,
+            pi,
+            buf: [0; WRITER_BUFFER],
+            len: 0,
+        }
+    }
+
+    pub fn flush(&mut self) -> Result<(), FlashcartError> {
+        if self.len == 0 {
+            return Ok(());
+        }
+
+        let len = core::mem::take(&mut self.len);
+
+        self.cart.send(self.pi, DataType::Text, &self.buf[..len])
+    }
+}
+
+impl fmt::Write for FlashcartWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut rest = s.as_bytes();
+
+        while !rest.is_empty() {
+            if self.len == WRITER_BUFFER {
+                self.flush().map_err(|_| fmt::Error)?;
+            }
+
+            let n = rest.len().min(WRITER_BUFFER - self.len);
+
+            self.buf[self.len..self.len + n].copy_from_slice(&rest[..n]);
+            self.len += n;
+            rest = &rest[n..];
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for FlashcartWriter<'_> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
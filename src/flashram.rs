@@ -0,0 +1,254 @@
+use crate::boot::ms_to_ticks;
+use crate::cop0::cop0;
+use crate::io_ptr;
+use crate::pi::{Domain, DomainTiming, Pi};
+use crate::types::Align8;
+
+const FLASH_BASE: u32 = 0x0800_0000;
+
+// reading the data window in status mode returns the status register
+const FLASH_STATUS: *mut u32 = io_ptr!(mut FLASH_BASE);
+const FLASH_CMD_ADDR: u32 = FLASH_BASE + 0x0001_0000;
+const FLASH_CMD: *mut u32 = io_ptr!(mut FLASH_CMD_ADDR);
+
+const CMD_STATUS: u32 = 0xD200_0000;
+const CMD_READ_ID: u32 = 0xE100_0000;
+const CMD_READ_ARRAY: u32 = 0xF000_0000;
+const CMD_LOAD_PAGE: u32 = 0xB400_0000;
+const CMD_PROGRAM: u32 = 0xA500_0000;
+const CMD_SECTOR_ERASE: u32 = 0x4B00_0000;
+const CMD_CHIP_ERASE: u32 = 0x3C00_0000;
+const CMD_EXECUTE_ERASE: u32 = 0x7800_0000;
+
+const STATUS_PROGRAM_BUSY: u32 = 1 << 0;
+const STATUS_ERASE_BUSY: u32 = 1 << 1;
+const STATUS_PROGRAM_OK: u32 = 1 << 2;
+const STATUS_ERASE_OK: u32 = 1 << 3;
+
+// the first word of the ID identifies the save type as FlashRAM, whoever made the chip
+const FLASH_TYPE_ID: u32 = 0x1111_8001;
+
+// generous compared to the datasheets, since some chips are much slower than others
+const PROGRAM_TIMEOUT_MS: u32 = 50;
+const SECTOR_ERASE_TIMEOUT_MS: u32 = 1_000;
+const CHIP_ERASE_TIMEOUT_MS: u32 = 10_000;
+
+pub const PAGE_SIZE: usize = 128;
+pub const PAGES: usize = 1024;
+pub const PAGES_PER_SECTOR: usize = 128;
+pub const SECTORS: usize = PAGES / PAGES_PER_SECTOR;
+pub const SIZE: usize = PAGES * PAGE_SIZE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlashRamError {
+    OutOfRange,
+    /// the chip was still busy when the timeout ran out
+    Timeout,
+    EraseFailed,
+    ProgramFailed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlashRamChip {
+    MacronixProtoA,
+    MacronixA,
+    MacronixBD,
+    MacronixC,
+    Matsushita,
+    Unknown(u32),
+}
+
+impl FlashRamChip {
+    fn from_id(id: u32) -> Self {
+        match id {
+            0x00C2_0000 => Self::MacronixProtoA,
+            0x00C2_0001 => Self::MacronixA,
+            0x00C2_001D => Self::MacronixBD,
+            0x00C2_001E => Self::MacronixC,
+            0x0032_00F1 => Self::Matsushita,
+            _ => Self::Unknown(id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FlashRam {
+    chip: FlashRamChip,
+}
+
+impl FlashRam {
+    fn command(pi: &mut Pi, cmd: u32) {
+        pi.wait();
+
+        unsafe { FLASH_CMD.write_volatile(cmd) }
+    }
+
+    fn status(pi: &mut Pi) -> u32 {
+        Self::command(pi, CMD_STATUS);
+
+        pi.wait();
+
+        unsafe { FLASH_STATUS.read_volatile() & 0xFF }
+    }
+
+    fn wait_while(pi: &mut Pi, busy: u32, timeout_ms: u32) -> Result<u32, FlashRamError> {
+        let cop0 = cop0();
+
+        let start = cop0.count();
+        let timeout = ms_to_ticks(timeout_ms);
+
+        loop {
+            let status = Self::status(pi);
+
+            if status & busy == 0 {
+                return Ok(status);
+            }
+
+            if cop0.count().wrapping_sub(start) >= timeout {
+                return Err(FlashRamError::Timeout);
+            }
+        }
+    }
+
+    /// configures domain 2 for FlashRAM and checks that there's one there
+    ///
+    /// asking for the ID means writing a command to `FLASH_CMD`, which on an SRAM cartridge lands
+    /// in the save itself; the word there is read first and put back if no FlashRAM answers, but
+    /// when detecting save types, SRAM should still be ruled out before this is tried
+    pub fn detect(pi: &mut Pi) -> Option<Self> {
+        pi.set_domain_timing(Domain::Dom2, DomainTiming::FLASHRAM);
+
+        let mut saved = Align8([0u32; 1]);
+        pi.read_into(&mut saved, FLASH_CMD_ADDR);
+
+        Self::command(pi, CMD_READ_ID);
+
+        let mut id = Align8([0u32; 2]);
+        pi.read_into(&mut id, FLASH_BASE);
+
+        if id.0[0] != FLASH_TYPE_ID {
+            // whatever is there took the command as data, so undo it
+            pi.write(&saved, FLASH_CMD_ADDR);
+            return None;
+        }
+
+        Self::command(pi, CMD_READ_ARRAY);
+
+        Some(Self {
+            chip: FlashRamChip::from_id(id.0[1]),
+        })
+    }
+
+    pub fn chip(&self) -> FlashRamChip {
+        self.chip
+    }
+
+    pub fn read_page(
+        &self,
+        pi: &mut Pi,
+        page: usize,
+        data: &mut [u8; PAGE_SIZE],
+    ) -> Result<(), FlashRamError> {
+        if page >= PAGES {
+            return Err(FlashRamError::OutOfRange);
+        }
+
+        Self::command(pi, CMD_READ_ARRAY);
+
+        let mut buf = Align8([0; PAGE_SIZE]);
+
+        // the array is addressed in halfwords
+        pi.read_into(&mut buf, FLASH_BASE + (page * PAGE_SIZE / 2) as u32);
+
+        *data = buf.0;
+
+        Ok(())
+    }
+
+    pub fn read(&self, pi: &mut Pi, offset: usize, data: &mut [u8]) -> Result<(), FlashRamError> {
+        if offset + data.len() > SIZE {
+            return Err(FlashRamError::OutOfRange);
+        }
+
+        let mut page = [0; PAGE_SIZE];
+        let mut done = 0;
+
+        while done < data.len() {
+            let pos = offset + done;
+            let skip = pos % PAGE_SIZE;
+            let len = (PAGE_SIZE - skip).min(data.len() - done);
+
+            self.read_page(pi, pos / PAGE_SIZE, &mut page)?;
+
+            data[done..done + len].copy_from_slice(&page[skip..skip + len]);
+
+            done += len;
+        }
+
+        Ok(())
+    }
+
+    fn check_erase(status: u32) -> Result<(), FlashRamError> {
+        if status & STATUS_ERASE_OK == 0 {
+            Err(FlashRamError::EraseFailed)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// erases `PAGES_PER_SECTOR` pages starting at `sector * PAGES_PER_SECTOR`, setting them to 0xFF
+    pub fn erase_sector(&self, pi: &mut Pi, sector: usize) -> Result<(), FlashRamError> {
+        if sector >= SECTORS {
+            return Err(FlashRamError::OutOfRange);
+        }
+
+        Self::command(pi, CMD_SECTOR_ERASE | (sector * PAGES_PER_SECTOR) as u32);
+        Self::command(pi, CMD_EXECUTE_ERASE);
+
+        let status = Self::wait_while(pi, STATUS_ERASE_BUSY, SECTOR_ERASE_TIMEOUT_MS)?;
+
+        Self::command(pi, CMD_READ_ARRAY);
+
+        Self::check_erase(status)
+    }
+
+    pub fn erase_chip(&self, pi: &mut Pi) -> Result<(), FlashRamError> {
+        Self::command(pi, CMD_CHIP_ERASE);
+        Self::command(pi, CMD_EXECUTE_ERASE);
+
+        let status = Self::wait_while(pi, STATUS_ERASE_BUSY, CHIP_ERASE_TIMEOUT_MS)?;
+
+        Self::command(pi, CMD_READ_ARRAY);
+
+        Self::check_erase(status)
+    }
+
+    /// programming can only clear bits, so the page's sector has to have been erased first
+    pub fn write_page(
+        &self,
+        pi: &mut Pi,
+        page: usize,
+        data: &[u8; PAGE_SIZE],
+    ) -> Result<(), FlashRamError> {
+        if page >= PAGES {
+            return Err(FlashRamError::OutOfRange);
+        }
+
+        let buf = Align8(*data);
+
+        Self::command(pi, CMD_LOAD_PAGE);
+        pi.write(&buf, FLASH_BASE);
+
+        Self::command(pi, CMD_PROGRAM | page as u32);
+
+        let status = Self::wait_while(pi, STATUS_PROGRAM_BUSY, PROGRAM_TIMEOUT_MS)?;
+
+        Self::command(pi, CMD_READ_ARRAY);
+
+        if status & STATUS_PROGRAM_OK == 0 {
+            Err(FlashRamError::ProgramFailed)
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,200 @@
+#[cfg(not(feature = "sk"))]
+use crate::boot::{interrupts::im, ms_to_ticks};
+#[cfg(not(feature = "sk"))]
+use crate::cop0::cop0;
+#[cfg(not(feature = "sk"))]
+use crate::pi::pi;
+use crate::pi::{LedValue, Pi};
+
+// each pin's output enable sits this far above its level
+const OUTPUT_ENABLE_SHIFT: u32 = 4;
+
+const BOARD_ID_SHIFT: u32 = 30;
+const BOARD_ID_MASK: u32 = 0b11;
+
+/// the pins on the BB's GPIO register, numbered by their bit
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pin {
+    /// holds the power supply on; dropping it turns the console off
+    Power = 0,
+    /// low to light the LED
+    Led = 1,
+    RtcClock = 2,
+    RtcData = 3,
+}
+
+impl Pin {
+    const fn level_bit(self) -> u32 {
+        1 << self as u32
+    }
+
+    const fn output_enable_bit(self) -> u32 {
+        1 << (self as u32 + OUTPUT_ENABLE_SHIFT)
+    }
+}
+
+/// which board the console has, from the ID bits at the top of the GPIO register
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BoardRevision {
+    /// ID 0, which libultra reports as `__osBbIsBb` 1
+    Original,
+    /// any other ID, which libultra reports as `__osBbIsBb` 2
+    Revised(u8),
+}
+
+impl BoardRevision {
+    pub const fn from_id(id: u8) -> Self {
+        match id {
+            0 => Self::Original,
+            id => Self::Revised(id),
+        }
+    }
+
+    pub const fn id(self) -> u8 {
+        match self {
+            Self::Original => 0,
+            Self::Revised(id) => id,
+        }
+    }
+}
+
+/// the BB's GPIO pins, on top of `PI_BB_GPIO`
+pub struct Gpio<'a> {
+    pi: &'a mut Pi,
+}
+
+impl Pi {
+    pub fn gpio(&mut self) -> Gpio<'_> {
+        Gpio { pi: self }
+    }
+}
+
+impl Gpio<'_> {
+    /// the level on the pin, whether it's being driven or not
+    pub fn level(&self, pin: Pin) -> bool {
+        self.pi.bb_gpio() & pin.level_bit() != 0
+    }
+
+    pub fn output_enabled(&self, pin: Pin) -> bool {
+        self.pi.bb_gpio() & pin.output_enable_bit() != 0
+    }
+
+    /// stops driving the pin, or starts driving it with whatever level was last set
+    pub fn set_output_enable(&mut self, pin: Pin, enable: bool) {
+        let prev = self.pi.bb_gpio() & !pin.output_enable_bit();
+        let new = prev | if enable { pin.output_enable_bit() } else { 0 };
+        self.pi.set_bb_gpio(new);
+    }
+
+    /// drives the pin, enabling its output if it wasn't already
+    pub fn set_level(&mut self, pin: Pin, level: bool) {
+        let prev = self.pi.bb_gpio() & !pin.level_bit();
+        let new = prev | pin.output_enable_bit() | if level { pin.level_bit() } else { 0 };
+        self.pi.set_bb_gpio(new);
+    }
+
+    pub fn board_revision(&self) -> BoardRevision {
+        BoardRevision::from_id(((self.pi.bb_gpio() >> BOARD_ID_SHIFT) & BOARD_ID_MASK) as u8)
+    }
+
+    pub fn set_led(&mut self, val: LedValue) {
+        self.set_level(Pin::Led, val == LedValue::Off);
+    }
+
+    pub fn power_off(&mut self) {
+        self.set_level(Pin::Power, false);
+    }
+
+    pub fn power_on(&mut self) {
+        self.set_level(Pin::Power, true);
+    }
+}
+
+/// what the LED does, with anything other than `Off` and `On` stepped through by the timer
+/// interrupt
+#[cfg(not(feature = "sk"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LedPattern {
+    Off,
+    On,
+    Blink {
+        on_ms: u32,
+        off_ms: u32,
+    },
+    /// each step is held for its time in milliseconds, then the whole sequence repeats
+    Sequence(&'static [(LedValue, u32)]),
+}
+
+#[cfg(not(feature = "sk"))]
+impl LedPattern {
+    fn step(self, index: usize) -> Option<(LedValue, u32)> {
+        match self {
+            Self::Off | Self::On => None,
+            Self::Blink { on_ms, off_ms } => Some(if index % 2 == 0 {
+                (LedValue::On, on_ms)
+            } else {
+                (LedValue::Off, off_ms)
+            }),
+            Self::Sequence(steps) if steps.is_empty() => None,
+            Self::Sequence(steps) => Some(steps[index % steps.len()]),
+        }
+    }
+}
+
+#[cfg(not(feature = "sk"))]
+static mut LED_PATTERN: LedPattern = LedPattern::Off;
+// the step the pattern is on, only touched by the timer interrupt once the pattern is running
+#[cfg(not(feature = "sk"))]
+static mut LED_STEP: usize = 0;
+
+/// shows the next step of the pattern and sets the timer for the one after
+#[cfg(not(feature = "sk"))]
+fn led_interrupt() -> bool {
+    let pattern = unsafe { (&raw const LED_PATTERN).read_volatile() };
+    let index = unsafe { (&raw const LED_STEP).read_volatile() };
+
+    if let Some((val, ms)) = pattern.step(index) {
+        pi().gpio().set_led(val);
+
+        let cop0 = cop0();
+        cop0.set_compare(cop0.count().wrapping_add(ms_to_ticks(ms.max(1))));
+
+        unsafe { (&raw mut LED_STEP).write_volatile(index.wrapping_add(1)) }
+    }
+
+    true
+}
+
+#[cfg(not(feature = "sk"))]
+impl Gpio<'_> {
+    /// starts the LED on a pattern, replacing any before it
+    ///
+    /// patterns that change over time take over the timer interrupt until `Off` or `On` is set
+    pub fn set_led_pattern(&mut self, pattern: LedPattern) {
+        let im = im();
+        im.set_tmr(false);
+        im.set_tmr_fn(None);
+
+        unsafe {
+            (&raw mut LED_PATTERN).write_volatile(pattern);
+            (&raw mut LED_STEP).write_volatile(0);
+        }
+
+        match pattern.step(0) {
+            Some(_) => {
+                // the first step is shown straight away, which also sets the timer for the next
+                led_interrupt();
+
+                im.set_tmr_fn(Some(led_interrupt));
+                im.set_tmr(true);
+            }
+            None if pattern == LedPattern::On => self.set_led(LedValue::On),
+            None => self.set_led(LedValue::Off),
+        }
+    }
+
+    pub fn led_pattern(&self) -> LedPattern {
+        unsafe { (&raw const LED_PATTERN).read_volatile() }
+    }
+}
@@ -0,0 +1,347 @@
+use crate::boot::interrupts::im;
+use crate::boot::ms_to_ticks;
+use crate::cop0::cop0;
+use crate::io_ptr;
+use crate::pi::{pi, Pi};
+
+// the dev boards decode IDE here, each 16-bit register on its own word
+const IDE_BASE: u32 = 0x046A_0000;
+// the control block, selected by CS1 rather than CS0
+const IDE_CONTROL_BASE: u32 = IDE_BASE + 0x8000;
+
+const IDE_DATA: *mut u32 = io_ptr!(mut IDE_BASE);
+const IDE_ERROR_FEATURES: *mut u32 = io_ptr!(mut IDE_BASE + 0x04);
+const IDE_SECTOR_COUNT: *mut u32 = io_ptr!(mut IDE_BASE + 0x08);
+const IDE_LBA_LOW: *mut u32 = io_ptr!(mut IDE_BASE + 0x0C);
+const IDE_LBA_MID: *mut u32 = io_ptr!(mut IDE_BASE + 0x10);
+const IDE_LBA_HIGH: *mut u32 = io_ptr!(mut IDE_BASE + 0x14);
+const IDE_DEVICE: *mut u32 = io_ptr!(mut IDE_BASE + 0x18);
+// reading the status acknowledges the drive's interrupt
+const IDE_STATUS_COMMAND: *mut u32 = io_ptr!(mut IDE_BASE + 0x1C);
+// the same status, without acknowledging anything
+const IDE_ALT_STATUS_CONTROL: *mut u32 = io_ptr!(mut IDE_CONTROL_BASE + 0x18);
+
+// set in `PI_BB_IDE_CONFIG` to hold the bus in reset
+const IDE_CONFIG_RESET: u32 = 0x8000_0000;
+
+const STATUS_ERROR: u8 = 1 << 0;
+const STATUS_DATA_REQUEST: u8 = 1 << 3;
+const STATUS_DEVICE_FAULT: u8 = 1 << 5;
+const STATUS_READY: u8 = 1 << 6;
+const STATUS_BUSY: u8 = 1 << 7;
+
+const CONTROL_INTERRUPT_DISABLE: u8 = 1 << 1;
+const CONTROL_SOFT_RESET: u8 = 1 << 2;
+
+// always set, plus the LBA bit, on top of the top nibble of the address
+const DEVICE_LBA: u8 = 0xE0;
+
+const CMD_READ_SECTORS: u8 = 0x20;
+const CMD_WRITE_SECTORS: u8 = 0x30;
+const CMD_FLUSH_CACHE: u8 = 0xE7;
+const CMD_IDENTIFY: u8 = 0xEC;
+
+pub const SECTOR_SIZE: usize = 512;
+// a sector count of 0 means 256
+pub const MAX_SECTORS: usize = 256;
+const MAX_LBA: u32 = 1 << 28;
+
+// spin-up, if the drive was asleep, is what takes the longest
+const TIMEOUT_MS: u32 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdeError {
+    NoDevice,
+    /// the drive was still busy when the timeout ran out
+    Timeout,
+    /// the command failed, with the contents of the error register
+    Command(u8),
+    DeviceFault,
+    OutOfRange,
+}
+
+/// the interesting parts of the drive's IDENTIFY DEVICE data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Identify {
+    /// ASCII, space-padded
+    pub model: [u8; 40],
+    pub serial: [u8; 20],
+    pub firmware: [u8; 8],
+    /// how many sectors can be reached with 28-bit LBAs
+    pub sectors: u32,
+}
+
+impl Identify {
+    fn parse(words: &[u16; SECTOR_SIZE / 2]) -> Self {
+        // strings hold two characters per word, the first in the high byte
+        fn string<const N: usize>(words: &[u16]) -> [u8; N] {
+            let mut out = [0; N];
+
+            for (pair, word) in out.chunks_exact_mut(2).zip(words) {
+                pair.copy_from_slice(&word.to_be_bytes());
+            }
+
+            out
+        }
+
+        Self {
+            serial: string(&words[10..20]),
+            firmware: string(&words[23..27]),
+            model: string(&words[27..47]),
+            sectors: words[60] as u32 | ((words[61] as u32) << 16),
+        }
+    }
+}
+
+// set by the interrupt handler, and cleared when a command is waited for
+static mut IDE_PENDING: bool = false;
+
+fn pending() -> bool {
+    unsafe { (&raw const IDE_PENDING).read_volatile() }
+}
+
+fn set_pending(val: bool) {
+    unsafe { (&raw mut IDE_PENDING).write_volatile(val) }
+}
+
+fn ide_interrupt() -> bool {
+    set_pending(true);
+    true
+}
+
+/// quiets the drive's interrupt, once the handler has run
+pub(crate) fn acknowledge_interrupt() {
+    BbIde::read_reg(pi(), IDE_STATUS_COMMAND);
+}
+
+/// the IDE bus on development boards, for a single drive in PIO mode
+pub struct BbIde {
+    interrupts: bool,
+}
+
+impl BbIde {
+    /// takes the bus out of reset and resets the drive, then checks that one answered
+    pub fn new(pi: &mut Pi) -> Result<Self, IdeError> {
+        pi.set_bb_ide_config(pi.bb_ide_config() & !IDE_CONFIG_RESET);
+
+        let ide = Self { interrupts: false };
+
+        Self::write_reg(
+            pi,
+            IDE_ALT_STATUS_CONTROL,
+            CONTROL_SOFT_RESET | CONTROL_INTERRUPT_DISABLE,
+        );
+        Self::write_reg(pi, IDE_ALT_STATUS_CONTROL, CONTROL_INTERRUPT_DISABLE);
+
+        // an empty bus floats high, which looks like every bit of the status is set, BSY included,
+        // so check before waiting on it
+        if ide.status(pi) == 0xFF {
+            return Err(IdeError::NoDevice);
+        }
+
+        ide.wait_not_busy(pi)?;
+
+        Ok(ide)
+    }
+
+    /// has the drive interrupt when a command needs attention, instead of being polled
+    pub fn enable_interrupts(&mut self, pi: &mut Pi) {
+        set_pending(false);
+
+        let im = im();
+        im.set_ide_fn(Some(ide_interrupt));
+        im.set_ide(true);
+
+        Self::write_reg(pi, IDE_ALT_STATUS_CONTROL, 0);
+
+        self.interrupts = true;
+    }
+
+    pub fn disable_interrupts(&mut self, pi: &mut Pi) {
+        Self::write_reg(pi, IDE_ALT_STATUS_CONTROL, CONTROL_INTERRUPT_DISABLE);
+
+        let im = im();
+        im.set_ide(false);
+        im.set_ide_fn(None);
+
+        self.interrupts = false;
+    }
+
+    fn read_reg(pi: &mut Pi, reg: *mut u32) -> u16 {
+        pi.wait();
+
+        unsafe { reg.read_volatile() as u16 }
+    }
+
+    fn write_reg(pi: &mut Pi, reg: *mut u32, val: u8) {
+        Self::write_reg16(pi, reg, val as u16)
+    }
+
+    fn write_reg16(pi: &mut Pi, reg: *mut u32, val: u16) {
+        pi.wait();
+
+        unsafe { reg.write_volatile(val as u32) }
+    }
+
+    /// the status, without acknowledging an interrupt
+    pub fn status(&self, pi: &mut Pi) -> u8 {
+        Self::read_reg(pi, IDE_ALT_STATUS_CONTROL) as u8
+    }
+
+    fn wait_not_busy(&self, pi: &mut Pi) -> Result<u8, IdeError> {
+        let cop0 = cop0();
+
+        let start = cop0.count();
+        let timeout = ms_to_ticks(TIMEOUT_MS);
+
+        loop {
+            let status = self.status(pi);
+
+            if status & STATUS_BUSY == 0 {
+                return Ok(status);
+            }
+
+            if cop0.count().wrapping_sub(start) >= timeout {
+                return Err(IdeError::Timeout);
+            }
+        }
+    }
+
+    /// waits for the drive to finish with the command or want data, by interrupt if they're
+    /// enabled
+    fn wait(&self, pi: &mut Pi) -> Result<u8, IdeError> {
+        // the drive has 400ns after a command to raise BSY, which one read covers
+        self.status(pi);
+
+        if self.interrupts {
+            let cop0 = cop0();
+
+            let start = cop0.count();
+            let timeout = ms_to_ticks(TIMEOUT_MS);
+
+            while !pending() {
+                if cop0.count().wrapping_sub(start) >= timeout {
+                    return Err(IdeError::Timeout);
+                }
+            }
+
+            set_pending(false);
+        }
+
+        let status = self.wait_not_busy(pi)?;
+
+        if status & STATUS_DEVICE_FAULT != 0 {
+            Err(IdeError::DeviceFault)
+        } else if status & STATUS_ERROR != 0 {
+            Err(IdeError::Command(
+                Self::read_reg(pi, IDE_ERROR_FEATURES) as u8
+            ))
+        } else {
+            Ok(status)
+        }
+    }
+
+    fn command(&self, pi: &mut Pi, cmd: u8, lba: u32, count: usize) -> Result<(), IdeError> {
+        let status = self.wait_not_busy(pi)?;
+
+        if status & STATUS_READY == 0 {
+            return Err(IdeError::NoDevice);
+        }
+
+        set_pending(false);
+
+        Self::write_reg(pi, IDE_ERROR_FEATURES, 0);
+        Self::write_reg(pi, IDE_SECTOR_COUNT, count as u8);
+        Self::write_reg(pi, IDE_LBA_LOW, lba as u8);
+        Self::write_reg(pi, IDE_LBA_MID, (lba >> 8) as u8);
+        Self::write_reg(pi, IDE_LBA_HIGH, (lba >> 16) as u8);
+        Self::write_reg(pi, IDE_DEVICE, DEVICE_LBA | ((lba >> 24) as u8 & 0x0F));
+        Self::write_reg(pi, IDE_STATUS_COMMAND, cmd);
+
+        Ok(())
+    }
+
+    fn read_data(pi: &mut Pi, sector: &mut [u8]) {
+        // the data register is little-endian
+        for pair in sector.chunks_exact_mut(2) {
+            pair.copy_from_slice(&Self::read_reg(pi, IDE_DATA).to_le_bytes());
+        }
+    }
+
+    fn write_data(pi: &mut Pi, sector: &[u8]) {
+        for pair in sector.chunks_exact(2) {
+            Self::write_reg16(pi, IDE_DATA, u16::from_le_bytes([pair[0], pair[1]]));
+        }
+    }
+
+    fn check_range(lba: u32, len: usize) -> Result<usize, IdeError> {
+        let count = len / SECTOR_SIZE;
+
+        if len % SECTOR_SIZE != 0
+            || count == 0
+            || count > MAX_SECTORS
+            || lba as u64 + count as u64 > MAX_LBA as u64
+        {
+            Err(IdeError::OutOfRange)
+        } else {
+            Ok(count)
+        }
+    }
+
+    pub fn identify(&mut self, pi: &mut Pi) -> Result<Identify, IdeError> {
+        self.command(pi, CMD_IDENTIFY, 0, 0)?;
+
+        let status = self.wait(pi)?;
+
+        if status & STATUS_DATA_REQUEST == 0 {
+            return Err(IdeError::NoDevice);
+        }
+
+        let mut words = [0; SECTOR_SIZE / 2];
+
+        for word in &mut words {
+            *word = Self::read_reg(pi, IDE_DATA);
+        }
+
+        Ok(Identify::parse(&words))
+    }
+
+    /// reads whole sectors, up to `MAX_SECTORS` at a time
+    pub fn read_sectors(&mut self, pi: &mut Pi, lba: u32, data: &mut [u8]) -> Result<(), IdeError> {
+        let count = Self::check_range(lba, data.len())?;
+
+        self.command(pi, CMD_READ_SECTORS, lba, count % MAX_SECTORS)?;
+
+        // the drive interrupts once for each sector it has ready
+        for sector in data.chunks_exact_mut(SECTOR_SIZE) {
+            self.wait(pi)?;
+
+            Self::read_data(pi, sector);
+        }
+
+        Ok(())
+    }
+
+    /// writes whole sectors, up to `MAX_SECTORS` at a time, and waits for them to reach the disk
+    pub fn write_sectors(&mut self, pi: &mut Pi, lba: u32, data: &[u8]) -> Result<(), IdeError> {
+        let count = Self::check_range(lba, data.len())?;
+
+        self.command(pi, CMD_WRITE_SECTORS, lba, count % MAX_SECTORS)?;
+
+        // the first sector is asked for without an interrupt, and each after that with one
+        for (i, sector) in data.chunks_exact(SECTOR_SIZE).enumerate() {
+            if i == 0 {
+                self.wait_not_busy(pi)?;
+            } else {
+                self.wait(pi)?;
+            }
+
+            Self::write_data(pi, sector);
+        }
+
+        self.wait(pi)?;
+
+        self.command(pi, CMD_FLUSH_CACHE, 0, 0)?;
+        self.wait(pi).map(|_| ())
+    }
+}
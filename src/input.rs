@@ -0,0 +1,243 @@
+use core::mem::discriminant;
+
+use crate::boot::ms_to_ticks;
+use crate::cop0::cop0;
+use crate::joybus::{ControllerData, ControllerStatus};
+use crate::si::Si;
+
+pub const PORTS: usize = 4;
+
+/// the range `stick` scales calibrated input to
+pub const STICK_MAX: i8 = 127;
+
+const DEFAULT_REPEAT_DELAY_MS: u32 = 400;
+const DEFAULT_REPEAT_RATE_MS: u32 = 80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StickCalibration {
+    pub x_min: i8,
+    pub x_max: i8,
+    pub y_min: i8,
+    pub y_max: i8,
+}
+
+impl StickCalibration {
+    /// roughly the range of an original controller in good condition
+    pub const DEFAULT: Self = Self {
+        x_min: -80,
+        x_max: 80,
+        y_min: -80,
+        y_max: 80,
+    };
+
+    fn scale(val: i8, min: i8, max: i8) -> i8 {
+        let limit = if val < 0 { -(min as i32) } else { max as i32 };
+
+        if limit <= 0 {
+            return 0;
+        }
+
+        ((val as i32 * STICK_MAX as i32) / limit).clamp(-(STICK_MAX as i32), STICK_MAX as i32) as i8
+    }
+}
+
+impl Default for StickCalibration {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotplugEvent {
+    Connected(ControllerStatus),
+    Disconnected,
+    /// same device, different status, e.g. an accessory was inserted or removed
+    Changed(ControllerStatus),
+}
+
+#[derive(Clone, Copy)]
+struct Port {
+    status: ControllerStatus,
+    current: Option<ControllerData>,
+    previous: Option<ControllerData>,
+    calibration: StickCalibration,
+    repeat_next: u32,
+    repeat: u16,
+}
+
+impl Port {
+    const fn new() -> Self {
+        Self {
+            status: ControllerStatus::None,
+            current: None,
+            previous: None,
+            calibration: StickCalibration::DEFAULT,
+            repeat_next: 0,
+            repeat: 0,
+        }
+    }
+
+    fn buttons(data: Option<ControllerData>) -> u16 {
+        data.map_or(0, |d| d.buttons())
+    }
+
+    fn update_status(&mut self, status: ControllerStatus) -> Option<HotplugEvent> {
+        let previous = self.status;
+        self.status = status;
+
+        let connected = |s| !matches!(s, ControllerStatus::None | ControllerStatus::Error);
+
+        match (connected(previous), connected(status)) {
+            (false, true) => Some(HotplugEvent::Connected(status)),
+            (true, false) => Some(HotplugEvent::Disconnected),
+            (true, true) if discriminant(&previous) != discriminant(&status) => {
+                Some(HotplugEvent::Connected(status))
+            }
+            (true, true) if previous != status => Some(HotplugEvent::Changed(status)),
+            _ => None,
+        }
+    }
+
+    fn update_repeat(&mut self, now: u32, delay: u32, rate: u32) {
+        let held = Self::buttons(self.current) & ControllerData::D_PAD;
+        let before = Self::buttons(self.previous) & ControllerData::D_PAD;
+
+        self.repeat = 0;
+
+        if held != before {
+            // a new direction restarts the delay
+            if held != 0 {
+                self.repeat = held;
+                self.repeat_next = now.wrapping_add(delay);
+            }
+        } else if held != 0 && now.wrapping_sub(self.repeat_next) as i32 >= 0 {
+            self.repeat = held;
+            self.repeat_next = now.wrapping_add(rate);
+        }
+    }
+}
+
+pub struct Input {
+    ports: [Port; PORTS],
+    dead_zone: i8,
+    repeat_delay: u32,
+    repeat_rate: u32,
+}
+
+impl Input {
+    pub fn new() -> Self {
+        Self {
+            ports: [Port::new(); PORTS],
+            dead_zone: 8,
+            repeat_delay: ms_to_ticks(DEFAULT_REPEAT_DELAY_MS),
+            repeat_rate: ms_to_ticks(DEFAULT_REPEAT_RATE_MS),
+        }
+    }
+
+    /// polls every port; call once per frame
+    ///
+    /// returns any changes in what's plugged into each port since the last update
+    pub fn update(&mut self, si: &mut Si) -> [Option<HotplugEvent>; PORTS] {
+        let statuses = si.query_controllers();
+        let data = si.read_controllers();
+
+        let now = cop0().count();
+
+        let mut events = [None; PORTS];
+
+        for (index, port) in self.ports.iter_mut().enumerate() {
+            events[index] = port.update_status(statuses[index]);
+
+            port.previous = port.current;
+            port.current = match (port.status, data[index]) {
+                (ControllerStatus::StandardController(_), Ok(data)) => data,
+                _ => None,
+            };
+
+            port.update_repeat(now, self.repeat_delay, self.repeat_rate);
+        }
+
+        events
+    }
+
+    pub fn set_dead_zone(&mut self, dead_zone: i8) {
+        self.dead_zone = dead_zone.clamp(0, STICK_MAX - 1);
+    }
+
+    pub fn set_calibration(&mut self, port: usize, calibration: StickCalibration) {
+        self.ports[port].calibration = calibration;
+    }
+
+    /// `delay_ms` is how long a direction has to be held before it starts repeating, and
+    /// `rate_ms` is the time between repeats after that
+    pub fn set_repeat(&mut self, delay_ms: u32, rate_ms: u32) {
+        self.repeat_delay = ms_to_ticks(delay_ms);
+        self.repeat_rate = ms_to_ticks(rate_ms);
+    }
+
+    pub fn status(&self, port: usize) -> ControllerStatus {
+        self.ports[port].status
+    }
+
+    pub fn data(&self, port: usize) -> Option<ControllerData> {
+        self.ports[port].current
+    }
+
+    pub fn held(&self, port: usize, buttons: u16) -> bool {
+        Port::buttons(self.ports[port].current) & buttons != 0
+    }
+
+    pub fn pressed(&self, port: usize, buttons: u16) -> bool {
+        let port = &self.ports[port];
+        (Port::buttons(port.current) & !Port::buttons(port.previous)) & buttons != 0
+    }
+
+    pub fn released(&self, port: usize, buttons: u16) -> bool {
+        let port = &self.ports[port];
+        (!Port::buttons(port.current) & Port::buttons(port.previous)) & buttons != 0
+    }
+
+    /// true on the frame a D-pad direction is pressed, then periodically while it stays held
+    pub fn repeated(&self, port: usize, buttons: u16) -> bool {
+        self.ports[port].repeat & buttons != 0
+    }
+
+    /// the stick position scaled by the port's calibration to `-STICK_MAX..=STICK_MAX`, with the
+    /// dead zone removed
+    pub fn stick(&self, port: usize) -> (i8, i8) {
+        let port = &self.ports[port];
+
+        let Some(data) = port.current else {
+            return (0, 0);
+        };
+
+        let cal = port.calibration;
+
+        let (x, y) = (
+            StickCalibration::scale(data.x(), cal.x_min, cal.x_max),
+            StickCalibration::scale(data.y(), cal.y_min, cal.y_max),
+        );
+
+        (self.apply_dead_zone(x), self.apply_dead_zone(y))
+    }
+
+    fn apply_dead_zone(&self, val: i8) -> i8 {
+        let dz = self.dead_zone as i32;
+        let mag = (val as i32).abs();
+
+        if mag <= dz {
+            return 0;
+        }
+
+        // rescale so the output still covers the full range outside the dead zone
+        let scaled = ((mag - dz) * STICK_MAX as i32) / (STICK_MAX as i32 - dz);
+
+        (scaled * (val as i32).signum()) as i8
+    }
+}
+
+impl Default for Input {
+    fn default() -> Self {
+        Self::new()
+    }
+}
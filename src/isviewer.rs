@@ -0,0 +1,65 @@
+use core::fmt;
+
+use crate::io_ptr;
+use crate::pi::Pi;
+
+const ISV_BASE: u32 = 0x13FF_0000;
+
+const ISV_MAGIC: *mut u32 = io_ptr!(mut ISV_BASE);
+// writing the length prints that much of the buffer
+const ISV_LENGTH: *mut u32 = io_ptr!(mut ISV_BASE + 0x14);
+const ISV_BUFFER: *mut [u32] = io_ptr!(mut ISV_BASE + 0x20; BUFFER_SIZE / 4);
+
+// "IS64"
+const ISV_MAGIC_VALUE: u32 = 0x4953_3634;
+
+pub const BUFFER_SIZE: usize = 0x200;
+
+/// text output through an IS-Viewer 64, which most emulators show in their log
+pub struct IsViewer<'a> {
+    pi: &'a mut Pi,
+}
+
+impl<'a> IsViewer<'a> {
+    /// the buffer is only writable if there's an IS-Viewer, so check the magic sticks; on a
+    /// plain cartridge the write goes nowhere and the ROM reads back instead
+    pub fn detect(pi: &'a mut Pi) -> Option<Self> {
+        pi.wait();
+
+        unsafe { ISV_MAGIC.write_volatile(ISV_MAGIC_VALUE) }
+
+        pi.wait();
+
+        if unsafe { ISV_MAGIC.read_volatile() } != ISV_MAGIC_VALUE {
+            return None;
+        }
+
+        Some(Self { pi })
+    }
+
+    pub fn write_bytes(&mut self, data: &[u8]) {
+        for chunk in data.chunks(BUFFER_SIZE) {
+            // the buffer only takes whole words, so the last one is padded
+            for (i, bytes) in chunk.chunks(4).enumerate() {
+                let mut word = [0; 4];
+                word[..bytes.len()].copy_from_slice(bytes);
+
+                self.pi.wait();
+
+                unsafe { (&raw mut (*ISV_BUFFER)[i]).write_volatile(u32::from_be_bytes(word)) }
+            }
+
+            self.pi.wait();
+
+            unsafe { ISV_LENGTH.write_volatile(chunk.len() as u32) }
+        }
+    }
+}
+
+impl fmt::Write for IsViewer<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_bytes(s.as_bytes());
+
+        Ok(())
+    }
+}
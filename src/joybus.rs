@@ -3,14 +3,30 @@ use core::num::Wrapping;
 use crate::boot::is_bbplayer;
 use crate::pi::{pi, LedValue};
 use crate::si::Si;
+#[cfg(not(feature = "sk"))]
+use crate::si::SiHandle;
 use crate::types::Align8;
 
+pub(crate) const EEPROM_CHANNEL: usize = 4;
+pub(crate) const RTC_CHANNEL: usize = 4;
+
 #[derive(Clone, Copy)]
-enum JoybusCommand {
+pub(crate) enum JoybusCommand {
     Info,
     ReadState,
     Reset,
     TxRx(u8, u8),
+    EepromRead(u8),
+    EepromWrite(u8, [u8; 8]),
+    RtcStatus,
+    RtcRead(u8),
+    RtcWrite(u8, [u8; 8]),
+    VruRead(u16),
+    VruWrite(u16, [u8; 20]),
+    VruStatus(u16),
+    VruConfig(u16, [u8; 4]),
+    VruInit(u8),
+    KeyboardPoll(u8),
 }
 
 impl JoybusCommand {
@@ -20,6 +36,17 @@ impl JoybusCommand {
             Self::ReadState => 1,
             Self::Reset => 1,
             Self::TxRx(_, _) => 3,
+            Self::EepromRead(_) => 2,
+            Self::EepromWrite(_, _) => 10,
+            Self::RtcStatus => 1,
+            Self::RtcRead(_) => 2,
+            Self::RtcWrite(_, _) => 10,
+            Self::VruRead(_) => 3,
+            Self::VruWrite(_, _) => 23,
+            Self::VruStatus(_) => 3,
+            Self::VruConfig(_, _) => 7,
+            Self::VruInit(_) => 3,
+            Self::KeyboardPoll(_) => 2,
         }
     }
 
@@ -29,6 +56,17 @@ impl JoybusCommand {
             Self::ReadState => 4,
             Self::Reset => 3,
             Self::TxRx(_, _) => 2,
+            Self::EepromRead(_) => 8,
+            Self::EepromWrite(_, _) => 1,
+            Self::RtcStatus => 3,
+            Self::RtcRead(_) => 9,
+            Self::RtcWrite(_, _) => 1,
+            Self::VruRead(_) => 37,
+            Self::VruWrite(_, _) => 1,
+            Self::VruStatus(_) => 3,
+            Self::VruConfig(_, _) => 1,
+            Self::VruInit(_) => 1,
+            Self::KeyboardPoll(_) => 7,
         }
     }
 
@@ -38,15 +76,119 @@ impl JoybusCommand {
             Self::ReadState => 0x01,
             Self::Reset => 0xFF,
             Self::TxRx(_, _) => 0x55,
+            Self::EepromRead(_) => 0x04,
+            Self::EepromWrite(_, _) => 0x05,
+            Self::RtcStatus => 0x06,
+            Self::RtcRead(_) => 0x07,
+            Self::RtcWrite(_, _) => 0x08,
+            Self::VruRead(_) => 0x09,
+            Self::VruWrite(_, _) => 0x0A,
+            Self::VruStatus(_) => 0x0B,
+            Self::VruConfig(_, _) => 0x0C,
+            Self::VruInit(_) => 0x0D,
+            Self::KeyboardPoll(_) => 0x13,
         }
     }
 
-    fn args(self) -> [u8; 4] {
+    fn write_args(self, mut write_byte: impl FnMut(u8)) {
         match self {
-            Self::Info | Self::ReadState | Self::Reset => [0xFF, 0xFF, 0xFF, 0xFF],
-            Self::TxRx(byte, index) => [byte, index, 0xFF, 0xFF],
+            Self::Info | Self::ReadState | Self::Reset | Self::RtcStatus => {}
+            Self::TxRx(byte, index) => {
+                write_byte(byte);
+                write_byte(index);
+            }
+            Self::EepromRead(block) | Self::RtcRead(block) => write_byte(block),
+            Self::EepromWrite(block, data) | Self::RtcWrite(block, data) => {
+                write_byte(block);
+                for b in data {
+                    write_byte(b);
+                }
+            }
+            Self::VruRead(addr) | Self::VruStatus(addr) => {
+                for b in address_with_crc(addr) {
+                    write_byte(b);
+                }
+            }
+            Self::VruWrite(addr, data) => {
+                for b in address_with_crc(addr) {
+                    write_byte(b);
+                }
+                for b in data {
+                    write_byte(b);
+                }
+            }
+            Self::VruConfig(addr, data) => {
+                for b in address_with_crc(addr) {
+                    write_byte(b);
+                }
+                for b in data {
+                    write_byte(b);
+                }
+            }
+            Self::VruInit(data) => {
+                write_byte(data);
+                write_byte(address_crc((data as u16) << 3));
+            }
+            Self::KeyboardPoll(leds) => write_byte(leds),
+        }
+    }
+}
+
+/// the 5-bit checksum used to protect addresses sent to accessories
+pub(crate) fn address_crc(addr: u16) -> u8 {
+    let mut crc = 0u32;
+
+    // addresses are 11 bits wide
+    for bit in (0..11).rev() {
+        crc <<= 1;
+        let carry = crc & 0x20 != 0;
+
+        if addr & (1 << bit) != 0 {
+            crc ^= if carry { 0x14 } else { 1 };
+        } else if carry {
+            crc ^= 0x15;
+        }
+    }
+
+    for _ in 0..5 {
+        crc <<= 1;
+        if crc & 0x20 != 0 {
+            crc ^= 0x15;
+        }
+    }
+
+    (crc & 0x1F) as u8
+}
+
+pub(crate) fn address_with_crc(addr: u16) -> [u8; 2] {
+    ((addr << 5) | address_crc(addr) as u16).to_be_bytes()
+}
+
+/// the 8-bit checksum accessories return after a read or write, so the transfer can be verified
+pub(crate) fn data_crc(data: &[u8]) -> u8 {
+    let mut crc = 0u32;
+
+    for byte in data {
+        for bit in (0..8).rev() {
+            crc <<= 1;
+            let carry = crc & 0x100 != 0;
+
+            if byte & (1 << bit) != 0 {
+                crc ^= if carry { 0x84 } else { 1 };
+            } else if carry {
+                crc ^= 0x85;
+            }
+        }
+    }
+
+    for _ in 0..8 {
+        crc <<= 1;
+        if crc & 0x100 != 0 {
+            crc ^= 0x85;
         }
     }
+
+    crc as u8
 }
 
 fn make_joybus_packet(cmds: [JoybusCommand; 4]) -> [u8; 64] {
@@ -65,8 +207,11 @@ fn make_joybus_packet(cmds: [JoybusCommand; 4]) -> [u8; 64] {
         write_byte(cmd.response_size());
         write_byte(cmd.cmd());
 
-        for b in cmd.args() {
-            write_byte(b);
+        cmd.write_args(&mut write_byte);
+
+        // pad each channel out to 8 bytes, leaving room for the response
+        for _ in cmd.cmd_size()..5 {
+            write_byte(0xFF);
         }
     }
 
@@ -82,9 +227,68 @@ fn make_joybus_packet_mult(cmd: JoybusCommand) -> [u8; 64] {
     make_joybus_packet([cmd, cmd, cmd, cmd])
 }
 
-#[derive(Clone, Copy)]
+/// builds a packet which sends a single command to one PIF channel, skipping the ones before it
+pub(crate) fn make_joybus_packet_single(channel: usize, cmd: JoybusCommand) -> [u8; 64] {
+    let mut packet = [0; 64];
+
+    let mut write_ptr = 0;
+
+    let mut write_byte = |b| {
+        packet[write_ptr] = b;
+        write_ptr += 1;
+    };
+
+    // 0x00 skips a channel
+    for _ in 0..channel {
+        write_byte(0x00);
+    }
+
+    write_byte(cmd.cmd_size());
+    write_byte(cmd.response_size());
+    write_byte(cmd.cmd());
+
+    cmd.write_args(&mut write_byte);
+
+    for _ in 0..cmd.response_size() {
+        write_byte(0xFF);
+    }
+
+    if !is_bbplayer() {
+        write_byte(0xFE);
+        packet[63] = 0x01;
+    }
+
+    packet
+}
+
+/// extracts the response to a packet built by `make_joybus_packet_single`
+///
+/// `Ok(None)` means nothing is connected to the channel
+pub(crate) fn parse_single_response(
+    cmd: JoybusCommand,
+    data: &[u8],
+    channel: usize,
+) -> Result<Option<&[u8]>, ()> {
+    // skipped channels are a single byte each
+    let offset = channel;
+
+    match data[offset + 1] & 0xC0 {
+        0x80 => return Ok(None),
+        0x40 | 0xC0 => return Err(()),
+        _ => {}
+    }
+
+    let start = offset + 2 + cmd.cmd_size() as usize;
+
+    Ok(Some(&data[start..start + cmd.response_size() as usize]))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ControllerStatus {
     StandardController(u8),
+    Mouse(u8),
+    Vru(u8),
+    Keyboard(u8),
     UnknownDevice(u16, u8),
     DebugProbe,
     None,
@@ -104,6 +308,9 @@ impl ControllerStatus {
         let device = u16::from_be_bytes([data[4], data[5]]);
         match device {
             0x0500 => Self::StandardController(data[6]),
+            0x0200 => Self::Mouse(data[6]),
+            0x0001 => Self::Vru(data[6]),
+            0x0002 => Self::Keyboard(data[6]),
             0xBB64 => Self::DebugProbe,
             0x0000 => Self::None,
             _ => Self::UnknownDevice(device, data[6]),
@@ -111,7 +318,7 @@ impl ControllerStatus {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ControllerData([u8; 4]);
 
 impl ControllerData {
@@ -132,6 +339,8 @@ impl ControllerData {
     pub const C_LEFT: u16 = 0x0002;
     pub const C_RIGHT: u16 = 0x0001;
 
+    pub const D_PAD: u16 = Self::D_UP | Self::D_DOWN | Self::D_LEFT | Self::D_RIGHT;
+
     fn parse_response(data: &[u8]) -> Result<Option<Self>, ()> {
         assert_eq!(data.len(), 8);
 
@@ -144,6 +353,15 @@ impl ControllerData {
         Ok(Some(Self([data[4], data[5], data[6], data[7]])))
     }
 
+    fn parse_all(response: &[u8]) -> [Result<Option<Self>, ()>; 4] {
+        [
+            Self::parse_response(&response[0..8]),
+            Self::parse_response(&response[8..16]),
+            Self::parse_response(&response[16..24]),
+            Self::parse_response(&response[24..32]),
+        ]
+    }
+
     pub fn x(&self) -> i8 {
         self.0[2] as _
     }
@@ -222,7 +440,14 @@ impl ControllerData {
 }
 
 impl Si {
-    #[cfg(not(feature = "sk"))]
+    pub(crate) fn transact_single(&mut self, channel: usize, cmd: JoybusCommand) -> [u8; 64] {
+        let packet = Align8(make_joybus_packet_single(channel, cmd));
+
+        self.write(&packet);
+
+        self.read()
+    }
+
     pub fn query_controllers(&mut self) -> [ControllerStatus; 4] {
         let packet = Align8(make_joybus_packet_mult(JoybusCommand::Info));
 
@@ -238,23 +463,6 @@ impl Si {
         ]
     }
 
-    /*#[cfg(feature = "sk")]
-    pub fn query_controllers(&mut self) -> [ControllerStatus; 4] {
-        unsafe { DRAM_BUF }.copy_from_slice(&make_joybus_packet_mult(JoybusCommand::Info));
-
-        self.write();
-
-        self.read();
-
-        [
-            ControllerStatus::parse_response(&unsafe { DRAM_BUF }[0..8]),
-            ControllerStatus::parse_response(&unsafe { DRAM_BUF }[8..16]),
-            ControllerStatus::parse_response(&unsafe { DRAM_BUF }[16..24]),
-            ControllerStatus::parse_response(&unsafe { DRAM_BUF }[24..32]),
-        ]
-    }*/
-
-    #[cfg(not(feature = "sk"))]
     pub fn read_controllers(&mut self) -> [Result<Option<ControllerData>, ()>; 4] {
         let packet = Align8(make_joybus_packet_mult(JoybusCommand::ReadState));
 
@@ -262,36 +470,34 @@ impl Si {
 
         let response = self.read();
 
-        [
-            ControllerData::parse_response(&response[0..8]),
-            ControllerData::parse_response(&response[8..16]),
-            ControllerData::parse_response(&response[16..24]),
-            ControllerData::parse_response(&response[24..32]),
-        ]
+        ControllerData::parse_all(&response)
     }
 
-    /*#[cfg(feature = "sk")]
-    pub fn read_controllers(&mut self) -> [Result<Option<ControllerData>, ()>; 4] {
-        unsafe { DRAM_BUF }.copy_from_slice(&make_joybus_packet_mult(JoybusCommand::ReadState));
-
-        self.write();
+    /// starts reading the controllers in the background; see `Si::start`
+    #[cfg(not(feature = "sk"))]
+    pub fn start_read_controllers(&mut self) -> Option<SiHandle> {
+        self.start(&make_joybus_packet_mult(JoybusCommand::ReadState))
+    }
 
-        self.read();
+    #[cfg(not(feature = "sk"))]
+    pub fn poll_read_controllers(
+        &mut self,
+        handle: SiHandle,
+    ) -> Option<[Result<Option<ControllerData>, ()>; 4]> {
+        let response = self.poll(handle)?;
 
-        [
-            ControllerData::parse_response(&unsafe { DRAM_BUF }[0..8]),
-            ControllerData::parse_response(&unsafe { DRAM_BUF }[8..16]),
-            ControllerData::parse_response(&unsafe { DRAM_BUF }[16..24]),
-            ControllerData::parse_response(&unsafe { DRAM_BUF }[24..32]),
-        ]
-    }*/
+        Some(ControllerData::parse_all(&response))
+    }
 
-    #[cfg(not(feature = "sk"))]
     pub fn txrx(&mut self, data: &[u8], mut out_buf: Option<&mut [u8]>) -> usize {
-        use crate::cop0::cop0;
+        #[cfg(not(feature = "sk"))]
+        let cop0 = crate::cop0::cop0();
 
-        let cop0 = cop0();
+        // an asynchronous transfer needs interrupts to finish
+        self.wait_idle();
 
+        // the secure kernel never takes interrupts, so there's nothing to disable there
+        #[cfg(not(feature = "sk"))]
         cop0.disable_interrupts();
 
         let (data_offset, count_offset) = if is_bbplayer() { (20, 21) } else { (22, 23) };
@@ -333,7 +539,7 @@ impl Si {
 
                 let resp = self.read();
 
-                if resp[23] == self.rx_index.0 {
+                if resp[count_offset] == self.rx_index.0 {
                     break;
                 }
 
@@ -343,60 +549,9 @@ impl Si {
             }
         }
 
+        #[cfg(not(feature = "sk"))]
         cop0.enable_interrupts();
 
         out_index
     }
-
-    /*#[cfg(feature = "sk")]
-    pub fn txrx(&mut self, data: &[u8], mut out_buf: Option<&mut [u8]>) -> usize {
-        let mut out_index = 0;
-
-        for byte in data {
-            self.tx_index += 1;
-            unsafe { DRAM_BUF }.copy_from_slice(&make_joybus_packet([
-                JoybusCommand::Info,
-                JoybusCommand::Info,
-                JoybusCommand::TxRx(*byte, self.tx_index.0),
-                JoybusCommand::Info,
-            ]));
-
-            self.write();
-
-            self.read();
-
-            if let Some(ref mut out) = out_buf {
-                if out_index < out.len() && unsafe { DRAM_BUF }[23] != self.rx_index.0 {
-                    self.rx_index = Wrapping(unsafe { DRAM_BUF }[23]);
-                    out[out_index] = unsafe { DRAM_BUF }[22];
-                    out_index += 1;
-                }
-            }
-        }
-
-        if let Some(ref mut out) = out_buf {
-            while out_index < out.len() {
-                unsafe { DRAM_BUF }.copy_from_slice(&make_joybus_packet([
-                    JoybusCommand::Info,
-                    JoybusCommand::Info,
-                    JoybusCommand::TxRx(0, self.tx_index.0),
-                    JoybusCommand::Info,
-                ]));
-
-                self.write();
-
-                self.read();
-
-                if unsafe { DRAM_BUF }[23] == self.rx_index.0 {
-                    break;
-                }
-
-                self.rx_index = Wrapping(unsafe { DRAM_BUF }[23]);
-                out[out_index] = unsafe { DRAM_BUF }[22];
-                out_index += 1;
-            }
-        }
-
-        out_index
-    }*/
 }
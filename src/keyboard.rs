@@ -0,0 +1,159 @@
+use crate::joybus::{parse_single_response, JoybusCommand};
+use crate::si::Si;
+
+pub const MAX_KEYS: usize = 3;
+
+pub const ROWS: usize = 16;
+pub const COLUMNS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyboardLeds(pub u8);
+
+impl KeyboardLeds {
+    pub const NUM_LOCK: u8 = 1 << 0;
+    pub const CAPS_LOCK: u8 = 1 << 1;
+    pub const POWER: u8 = 1 << 2;
+}
+
+/// a position in the key matrix; the keyboard reports which positions are down, not characters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyCode {
+    pub row: u8,
+    pub column: u8,
+}
+
+impl KeyCode {
+    fn parse(hi: u8, lo: u8) -> Option<Self> {
+        // rows and columns are numbered from 1, so zero means "no key"
+        if hi == 0 || lo == 0 {
+            None
+        } else {
+            Some(Self {
+                row: hi - 1,
+                column: lo - 1,
+            })
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct KeyboardData {
+    pub keys: [Option<KeyCode>; MAX_KEYS],
+    pub status: u8,
+}
+
+impl KeyboardData {
+    pub const HOME: u8 = 1 << 0;
+    /// more keys are down than can be reported; `keys` is unreliable
+    pub const OVERFLOW: u8 = 1 << 4;
+
+    pub fn is_down(&self, code: KeyCode) -> bool {
+        self.keys.contains(&Some(code))
+    }
+}
+
+/// maps matrix positions to characters, with a second table for when shift is held
+pub struct Keymap {
+    pub normal: [[u8; COLUMNS]; ROWS],
+    pub shifted: [[u8; COLUMNS]; ROWS],
+    pub shift: KeyCode,
+}
+
+impl Keymap {
+    /// returns 0 for positions which don't produce a character
+    pub fn lookup(&self, code: KeyCode, shift: bool) -> u8 {
+        let table = if shift { &self.shifted } else { &self.normal };
+
+        table
+            .get(code.row as usize)
+            .and_then(|row| row.get(code.column as usize))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+/// keeps track of the keyboard between polls, so newly pressed keys can be turned into characters
+pub struct KeyboardState {
+    pub leds: KeyboardLeds,
+    previous: KeyboardData,
+}
+
+impl KeyboardState {
+    pub const fn new() -> Self {
+        Self {
+            leds: KeyboardLeds(0),
+            previous: KeyboardData {
+                keys: [None; MAX_KEYS],
+                status: 0,
+            },
+        }
+    }
+
+    /// polls the keyboard on `port`, updating its LEDs, and returns the characters for any keys
+    /// that went down since the last poll
+    pub fn poll(
+        &mut self,
+        si: &mut Si,
+        port: usize,
+        keymap: &Keymap,
+    ) -> Result<[Option<u8>; MAX_KEYS], ()> {
+        let Some(data) = si.read_keyboard(port, self.leds)? else {
+            self.previous = KeyboardData::default();
+            return Ok([None; MAX_KEYS]);
+        };
+
+        let mut chars = [None; MAX_KEYS];
+
+        if data.status & KeyboardData::OVERFLOW == 0 {
+            let shift = data.is_down(keymap.shift);
+
+            for (index, code) in data.keys.into_iter().enumerate() {
+                let Some(code) = code else {
+                    continue;
+                };
+
+                if code == keymap.shift || self.previous.is_down(code) {
+                    continue;
+                }
+
+                chars[index] = match keymap.lookup(code, shift) {
+                    0 => None,
+                    ch => Some(ch),
+                };
+            }
+
+            self.previous = data;
+        }
+
+        Ok(chars)
+    }
+}
+
+impl Default for KeyboardState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Si {
+    pub fn read_keyboard(
+        &mut self,
+        port: usize,
+        leds: KeyboardLeds,
+    ) -> Result<Option<KeyboardData>, ()> {
+        let cmd = JoybusCommand::KeyboardPoll(leds.0);
+
+        let response = self.transact_single(port, cmd);
+
+        Ok(
+            parse_single_response(cmd, &response, port)?.map(|data| KeyboardData {
+                keys: [
+                    KeyCode::parse(data[0], data[1]),
+                    KeyCode::parse(data[2], data[3]),
+                    KeyCode::parse(data[4], data[5]),
+                ],
+                status: data[6],
+            }),
+        )
+    }
+}
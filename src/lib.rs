@@ -11,24 +11,56 @@ use core::arch::asm;
 use core::ops::Range;
 
 pub mod aes;
+#[cfg(feature = "sk")]
+pub mod bbsave;
 pub mod boot;
 pub mod card;
 pub mod cop0;
+#[cfg(not(feature = "sk"))]
+pub mod dd;
+#[cfg(not(feature = "sk"))]
+pub mod dma;
+#[cfg(not(feature = "sk"))]
+pub mod eeprom;
+#[cfg(not(feature = "sk"))]
+pub mod flashcart;
+#[cfg(not(feature = "sk"))]
+pub mod flashram;
+pub mod gpio;
+#[cfg(not(feature = "sk"))]
+pub mod ide;
+pub mod input;
+#[cfg(not(feature = "sk"))]
+pub mod isviewer;
 pub mod joybus;
+#[cfg(not(feature = "sk"))]
+pub mod keyboard;
+pub mod logger;
 pub mod mi;
+#[cfg(not(feature = "sk"))]
+pub mod mouse;
 #[cfg(feature = "alloc")]
 mod n64_alloc;
 pub mod pi;
+pub mod probe;
 pub mod recrypt;
 pub mod ri;
+#[cfg(not(feature = "sk"))]
+pub mod rom;
+#[cfg(not(feature = "sk"))]
+pub mod rtc;
 pub mod si;
 pub mod skapi;
+#[cfg(not(feature = "sk"))]
+pub mod sram;
 pub mod text;
 pub mod types;
 pub mod usb;
 pub mod util;
 pub mod v2;
 pub mod vi;
+#[cfg(not(feature = "sk"))]
+pub mod vru;
 
 #[macro_export]
 macro_rules! io_ptr {
@@ -68,13 +100,12 @@ macro_rules! cache {
 pub fn data_cache_writeback<T>(data: &[T]) {
     let Range { start, end } = data.as_ptr_range();
 
-    for i in (start.addr()..end.addr()).step_by(0x10) {
-        cache!(data, 6, i);
-    }
+    data_cache_writeback_raw(start.addr(), end.addr());
 }
 
 pub fn data_cache_writeback_raw(start: usize, end: usize) {
-    for i in (start..end).step_by(0x10) {
+    // every line the range touches, even if it starts or ends partway through one
+    for i in (start & !0xF..end).step_by(0x10) {
         cache!(data, 6, i);
     }
 }
@@ -82,8 +113,25 @@ pub fn data_cache_writeback_raw(start: usize, end: usize) {
 pub fn data_cache_invalidate<T>(data: &[T]) {
     let Range { start, end } = data.as_ptr_range();
 
-    for i in (start.addr()..end.addr()).step_by(0x10) {
-        cache!(data, 4, i);
+    data_cache_invalidate_raw(start.addr(), end.addr());
+}
+
+/// drops the range from the cache without writing it back, apart from the lines at either end
+/// that it only partly covers, which are written back first so whatever else shares them is kept
+pub fn data_cache_invalidate_raw(start: usize, end: usize) {
+    if start >= end {
+        return;
+    }
+
+    let first = start & !0xF;
+    let last = (end - 1) & !0xF;
+
+    for i in (first..=last).step_by(0x10) {
+        if i < start || i + 0x10 > end {
+            cache!(data, 5, i);
+        } else {
+            cache!(data, 4, i);
+        }
     }
 }
 
@@ -0,0 +1,73 @@
+use core::fmt::Write;
+
+use log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+#[cfg(not(feature = "sk"))]
+use crate::boot::is_bbplayer;
+use crate::boot::ms_to_ticks;
+use crate::cop0::cop0;
+#[cfg(not(feature = "sk"))]
+use crate::flashcart::{self, flashcart, FlashcartWriter};
+#[cfg(not(feature = "sk"))]
+use crate::pi::pi;
+use crate::probe::DebugWriter;
+use crate::si::si;
+
+/// sends `log` records to the debug probe, or to a flashcart's USB port on a retail console,
+/// stamped with the time since the count register last wrapped
+pub struct ProbeLogger;
+
+static LOGGER: ProbeLogger = ProbeLogger;
+
+/// installs the logger; anything less severe than `level` is dropped before it's formatted
+pub fn init(level: LevelFilter) -> Result<(), SetLoggerError> {
+    log::set_logger(&LOGGER)?;
+
+    #[cfg(not(feature = "sk"))]
+    if !is_bbplayer() {
+        flashcart::init(pi());
+    }
+    log::set_max_level(level);
+
+    Ok(())
+}
+
+pub fn set_level(level: LevelFilter) {
+    log::set_max_level(level);
+}
+
+impl Log for ProbeLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let ms = cop0().count() / ms_to_ticks(1);
+
+        #[cfg(not(feature = "sk"))]
+        if let Some(cart) = flashcart() {
+            write_record(&mut FlashcartWriter::new(cart, pi()), ms, record);
+            return;
+        }
+
+        write_record(&mut DebugWriter::new(si()), ms, record);
+    }
+
+    fn flush(&self) {}
+}
+
+fn write_record(out: &mut impl Write, ms: u32, record: &Record) {
+    let _ = writeln!(
+        out,
+        "[{:5}.{:03}] {:<5} {}: {}",
+        ms / 1000,
+        ms % 1000,
+        record.level(),
+        record.target(),
+        record.args()
+    );
+}
@@ -0,0 +1,69 @@
+use crate::joybus::{parse_single_response, ControllerData, JoybusCommand};
+use crate::si::Si;
+
+/// a single poll of the N64 Mouse
+///
+/// the buttons use the same bits as a controller, and the stick axes report how far the mouse
+/// has moved since the last poll
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseData {
+    pub buttons: u16,
+    pub dx: i8,
+    pub dy: i8,
+}
+
+impl MouseData {
+    pub fn left(&self) -> bool {
+        self.buttons & ControllerData::A != 0
+    }
+
+    pub fn right(&self) -> bool {
+        self.buttons & ControllerData::B != 0
+    }
+}
+
+/// tracks an absolute pointer position from the mouse's relative motion, clamped to a rectangle
+/// (usually the screen)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseState {
+    pub x: i32,
+    pub y: i32,
+    pub buttons: u16,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl MouseState {
+    pub const fn new(width: i32, height: i32) -> Self {
+        Self {
+            x: width / 2,
+            y: height / 2,
+            buttons: 0,
+            width,
+            height,
+        }
+    }
+
+    pub fn apply(&mut self, data: MouseData) {
+        self.x = (self.x + data.dx as i32).clamp(0, self.width - 1);
+        // up is positive on the mouse, but down is positive on the screen
+        self.y = (self.y - data.dy as i32).clamp(0, self.height - 1);
+        self.buttons = data.buttons;
+    }
+}
+
+impl Si {
+    pub fn read_mouse(&mut self, port: usize) -> Result<Option<MouseData>, ()> {
+        let cmd = JoybusCommand::ReadState;
+
+        let response = self.transact_single(port, cmd);
+
+        Ok(
+            parse_single_response(cmd, &response, port)?.map(|data| MouseData {
+                buttons: u16::from_be_bytes([data[0], data[1]]),
+                dx: data[2] as i8,
+                dy: data[3] as i8,
+            }),
+        )
+    }
+}
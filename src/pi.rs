@@ -2,6 +2,7 @@ use core::{
     array::from_fn,
     mem::{size_of, size_of_val},
     ops::Not,
+    slice::from_raw_parts_mut,
 };
 
 #[cfg(not(feature = "sk"))]
@@ -17,6 +18,14 @@ const PI_CART_ADDR: *mut u32 = io_ptr!(mut PI_BASE + 0x04);
 const PI_RD_LEN: *mut u32 = io_ptr!(mut PI_BASE + 0x08);
 const PI_WR_LEN: *mut u32 = io_ptr!(mut PI_BASE + 0x0C);
 const PI_STATUS: *mut u32 = io_ptr!(mut PI_BASE + 0x10);
+const PI_BSD_DOM1_LAT: *mut u32 = io_ptr!(mut PI_BASE + 0x14);
+const PI_BSD_DOM1_PWD: *mut u32 = io_ptr!(mut PI_BASE + 0x18);
+const PI_BSD_DOM1_PGS: *mut u32 = io_ptr!(mut PI_BASE + 0x1C);
+const PI_BSD_DOM1_RLS: *mut u32 = io_ptr!(mut PI_BASE + 0x20);
+const PI_BSD_DOM2_LAT: *mut u32 = io_ptr!(mut PI_BASE + 0x24);
+const PI_BSD_DOM2_PWD: *mut u32 = io_ptr!(mut PI_BASE + 0x28);
+const PI_BSD_DOM2_PGS: *mut u32 = io_ptr!(mut PI_BASE + 0x2C);
+const PI_BSD_DOM2_RLS: *mut u32 = io_ptr!(mut PI_BASE + 0x30);
 
 const PI_BB_ATB_UPPER: *mut u32 = io_ptr!(mut PI_BASE + 0x40);
 const PI_BB_NAND_CTRL: *mut u32 = io_ptr!(mut PI_BASE + 0x48);
@@ -33,6 +42,69 @@ const PI_BB_NAND_ADDR: *mut u32 = io_ptr!(mut PI_BASE + 0x70);
 
 const PI_BB_ATB_LOWER: *mut [u32] = io_ptr!(mut PI_BASE + 0x500; 192);
 
+// the first word of the ROM header holds the timings the boot code sets for domain 1
+const ROM_HEADER_CONFIG: *mut u32 = io_ptr!(mut 0x1000_0000);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Domain {
+    /// the cartridge ROM, and the 64DD
+    Dom1,
+    /// cartridge SRAM and FlashRAM, and the 64DD's registers
+    Dom2,
+}
+
+/// bus timings for a PI domain; every field is in RCP cycles, except `page_size`, which is the
+/// log2 of the page size in bytes minus 2
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DomainTiming {
+    pub latency: u8,
+    pub pulse_width: u8,
+    pub page_size: u8,
+    pub release: u8,
+}
+
+impl DomainTiming {
+    /// what retail ROM headers ask for
+    pub const ROM: Self = Self {
+        latency: 0x40,
+        pulse_width: 0x12,
+        page_size: 0x07,
+        release: 0x03,
+    };
+
+    pub const SRAM: Self = Self {
+        latency: 0x05,
+        pulse_width: 0x0C,
+        page_size: 0x0D,
+        release: 0x02,
+    };
+
+    pub const FLASHRAM: Self = Self {
+        latency: 0x05,
+        pulse_width: 0x0C,
+        page_size: 0x0F,
+        release: 0x02,
+    };
+
+    /// what libleo uses for the 64DD's registers and buffers
+    pub const DD: Self = Self {
+        latency: 0x03,
+        pulse_width: 0x06,
+        page_size: 0x06,
+        release: 0x02,
+    };
+
+    /// decodes the first word of a ROM header, which looks like `0x80371240`
+    pub const fn from_header_word(word: u32) -> Self {
+        Self {
+            latency: word as u8,
+            pulse_width: (word >> 8) as u8,
+            page_size: ((word >> 16) & 0x0F) as u8,
+            release: ((word >> 20) & 0x03) as u8,
+        }
+    }
+}
+
 #[repr(u32)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum LedValue {
@@ -66,9 +138,98 @@ impl Pi {
         self.set_bb_ide_config(self.bb_ide_config() & !0x80000000);
     }
 
+    pub fn domain_timing(&self, domain: Domain) -> DomainTiming {
+        let (lat, pwd, pgs, rls) = match domain {
+            Domain::Dom1 => (
+                self.bsd_dom1_lat(),
+                self.bsd_dom1_pwd(),
+                self.bsd_dom1_pgs(),
+                self.bsd_dom1_rls(),
+            ),
+            Domain::Dom2 => (
+                self.bsd_dom2_lat(),
+                self.bsd_dom2_pwd(),
+                self.bsd_dom2_pgs(),
+                self.bsd_dom2_rls(),
+            ),
+        };
+
+        DomainTiming {
+            latency: lat as u8,
+            pulse_width: pwd as u8,
+            page_size: pgs as u8,
+            release: rls as u8,
+        }
+    }
+
+    /// waits for any DMA in flight to finish first, since changing timings under one corrupts it
+    pub fn set_domain_timing(&mut self, domain: Domain, timing: DomainTiming) {
+        #[cfg(not(feature = "sk"))]
+        self.wait_queue();
+        self.wait();
+
+        let DomainTiming {
+            latency,
+            pulse_width,
+            page_size,
+            release,
+        } = timing;
+
+        match domain {
+            Domain::Dom1 => {
+                self.set_bsd_dom1_lat(latency as u32);
+                self.set_bsd_dom1_pwd(pulse_width as u32);
+                self.set_bsd_dom1_pgs(page_size as u32);
+                self.set_bsd_dom1_rls(release as u32);
+            }
+            Domain::Dom2 => {
+                self.set_bsd_dom2_lat(latency as u32);
+                self.set_bsd_dom2_pwd(pulse_width as u32);
+                self.set_bsd_dom2_pgs(page_size as u32);
+                self.set_bsd_dom2_rls(release as u32);
+            }
+        }
+    }
+
+    /// runs `f` with `domain` switched to `timing`, then puts the old timings back once the PI is
+    /// idle again
+    pub fn with_domain_timing<R>(
+        &mut self,
+        domain: Domain,
+        timing: DomainTiming,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        let previous = self.domain_timing(domain);
+
+        self.set_domain_timing(domain, timing);
+
+        let result = f(self);
+
+        self.set_domain_timing(domain, previous);
+
+        result
+    }
+
+    /// the domain 1 timings the cartridge asks for in its header
+    pub fn rom_header_timing(&mut self) -> DomainTiming {
+        self.wait();
+
+        DomainTiming::from_header_word(unsafe { ROM_HEADER_CONFIG.read_volatile() })
+    }
+
+    /// sets domain 1 up as the cartridge header asks, for carts that can go faster than the
+    /// boot code left them
+    pub fn apply_rom_header_timing(&mut self) {
+        let timing = self.rom_header_timing();
+
+        self.set_domain_timing(Domain::Dom1, timing);
+    }
+
     #[cfg(not(feature = "sk"))]
     #[track_caller]
     pub fn write<T>(&mut self, data: &Align8<[T]>, addr: u32) {
+        self.wait_queue();
+
         let len = size_of_val(&data.0);
 
         assert!(
@@ -103,6 +264,8 @@ impl Pi {
     #[cfg(not(feature = "sk"))]
     #[track_caller]
     pub fn read_into<T>(&mut self, data: &mut Align8<[T]>, addr: u32) {
+        self.wait_queue();
+
         let len = size_of_val(&data.0);
 
         data_cache_invalidate(&data.0);
@@ -129,6 +292,9 @@ impl Pi {
 
     #[track_caller]
     pub fn bb_write<T>(&mut self, data: &Align8<[T]>, addr: u32) {
+        #[cfg(not(feature = "sk"))]
+        self.wait_queue();
+
         let len = size_of_val(&data.0);
 
         assert!(
@@ -161,6 +327,9 @@ impl Pi {
 
     #[track_caller]
     pub fn bb_read_into<T>(&mut self, data: &mut Align8<[T]>, addr: u32) {
+        #[cfg(not(feature = "sk"))]
+        self.wait_queue();
+
         let len = size_of_val(&data.0);
 
         assert!(
@@ -203,6 +372,38 @@ impl Pi {
         unsafe { PI_STATUS.read_volatile() }
     }
 
+    pub fn bsd_dom1_lat(&self) -> u32 {
+        unsafe { PI_BSD_DOM1_LAT.read_volatile() }
+    }
+
+    pub fn bsd_dom1_pwd(&self) -> u32 {
+        unsafe { PI_BSD_DOM1_PWD.read_volatile() }
+    }
+
+    pub fn bsd_dom1_pgs(&self) -> u32 {
+        unsafe { PI_BSD_DOM1_PGS.read_volatile() }
+    }
+
+    pub fn bsd_dom1_rls(&self) -> u32 {
+        unsafe { PI_BSD_DOM1_RLS.read_volatile() }
+    }
+
+    pub fn bsd_dom2_lat(&self) -> u32 {
+        unsafe { PI_BSD_DOM2_LAT.read_volatile() }
+    }
+
+    pub fn bsd_dom2_pwd(&self) -> u32 {
+        unsafe { PI_BSD_DOM2_PWD.read_volatile() }
+    }
+
+    pub fn bsd_dom2_pgs(&self) -> u32 {
+        unsafe { PI_BSD_DOM2_PGS.read_volatile() }
+    }
+
+    pub fn bsd_dom2_rls(&self) -> u32 {
+        unsafe { PI_BSD_DOM2_RLS.read_volatile() }
+    }
+
     pub fn bb_atb_upper(&self) -> u32 {
         unsafe { PI_BB_ATB_UPPER.read_volatile() }
     }
@@ -297,6 +498,38 @@ impl Pi {
         unsafe { PI_STATUS.write_volatile(val) }
     }
 
+    pub fn set_bsd_dom1_lat(&mut self, val: u32) {
+        unsafe { PI_BSD_DOM1_LAT.write_volatile(val) }
+    }
+
+    pub fn set_bsd_dom1_pwd(&mut self, val: u32) {
+        unsafe { PI_BSD_DOM1_PWD.write_volatile(val) }
+    }
+
+    pub fn set_bsd_dom1_pgs(&mut self, val: u32) {
+        unsafe { PI_BSD_DOM1_PGS.write_volatile(val) }
+    }
+
+    pub fn set_bsd_dom1_rls(&mut self, val: u32) {
+        unsafe { PI_BSD_DOM1_RLS.write_volatile(val) }
+    }
+
+    pub fn set_bsd_dom2_lat(&mut self, val: u32) {
+        unsafe { PI_BSD_DOM2_LAT.write_volatile(val) }
+    }
+
+    pub fn set_bsd_dom2_pwd(&mut self, val: u32) {
+        unsafe { PI_BSD_DOM2_PWD.write_volatile(val) }
+    }
+
+    pub fn set_bsd_dom2_pgs(&mut self, val: u32) {
+        unsafe { PI_BSD_DOM2_PGS.write_volatile(val) }
+    }
+
+    pub fn set_bsd_dom2_rls(&mut self, val: u32) {
+        unsafe { PI_BSD_DOM2_RLS.write_volatile(val) }
+    }
+
     pub fn set_bb_atb_upper(&mut self, val: u32) {
         unsafe { PI_BB_ATB_UPPER.write_volatile(val) }
     }
@@ -372,25 +605,48 @@ impl Pi {
     }
 
     pub fn set_led(&mut self, val: LedValue) {
-        let prev = self.bb_gpio() & !(1 << 1);
-        let new = prev | (1 << 5) | ((val as u32) << 1);
-        self.set_bb_gpio(new);
+        self.gpio().set_led(val);
     }
 
     pub fn power_off(&mut self) {
-        let prev = self.bb_gpio() & !(1 << 0);
-        let new = prev | (1 << 4) | (0 << 0);
-        self.set_bb_gpio(new);
+        self.gpio().power_off();
     }
 
     #[cfg(feature = "sk")]
     pub fn power_on(&mut self) {
-        let prev = self.bb_gpio();
-        let new = prev | (1 << 4) | (1 << 0);
-        self.set_bb_gpio(new);
+        self.gpio().power_on();
+    }
+}
+
+#[cfg(not(feature = "sk"))]
+impl n64_transfer::PiRead for Pi {
+    unsafe fn dma_read(&mut self, dst: *mut u8, cart: u32, len: usize) {
+        let data = from_raw_parts_mut(dst, len);
+
+        // the destination can share cache lines with data that isn't being read over
+        data_cache_writeback(data);
+        data_cache_invalidate(data);
+
+        self.wait_queue();
+        self.wait();
+
+        self.set_dram_addr(k0_to_phys_mut(dst).addr() as _);
+        self.set_cart_addr(cart);
+        self.set_wr_len((len - 1) as _);
+
+        self.wait();
+
+        data_cache_invalidate(data);
     }
 }
 
+/// reads `dst.len()` bytes from `cart_addr`, without any of the alignment requirements of
+/// `Pi::read_into`
+#[cfg(not(feature = "sk"))]
+pub fn pi_read(dst: &mut [u8], cart_addr: u32) {
+    n64_transfer::read(pi(), dst, cart_addr)
+}
+
 static mut PI: Pi = Pi::new();
 
 pub fn pi() -> &'static mut Pi {
@@ -0,0 +1,59 @@
+use core::fmt;
+
+use n64_link::Transport;
+
+use crate::boot::ms_to_ticks;
+use crate::cop0::cop0;
+use crate::si::Si;
+
+pub use n64_link::{Frame, Link, LinkError, MAX_PAYLOAD};
+
+/// the 0xBB64 debug probe as a byte transport, for use with `Link`
+///
+/// `Si::txrx` has no framing of its own, so raw output and a `Link` shouldn't share the probe
+pub struct Probe<'a> {
+    si: &'a mut Si,
+}
+
+impl<'a> Probe<'a> {
+    pub fn new(si: &'a mut Si) -> Self {
+        Self { si }
+    }
+
+    pub fn link(si: &'a mut Si) -> Link<Self> {
+        Link::new(Self::new(si))
+    }
+}
+
+impl Transport for Probe<'_> {
+    fn exchange(&mut self, tx: &[u8], rx: &mut [u8]) -> usize {
+        self.si.txrx(tx, Some(rx))
+    }
+
+    fn now(&mut self) -> u32 {
+        cop0().count()
+    }
+
+    fn ticks_per_ms(&self) -> u32 {
+        ms_to_ticks(1)
+    }
+}
+
+/// raw text output over the debug probe, so `write!` works without `alloc`
+pub struct DebugWriter<'a> {
+    si: &'a mut Si,
+}
+
+impl<'a> DebugWriter<'a> {
+    pub fn new(si: &'a mut Si) -> Self {
+        Self { si }
+    }
+}
+
+impl fmt::Write for DebugWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.si.txrx(s.as_bytes(), None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,155 @@
+use crate::pi::{DomainTiming, Pi};
+use crate::types::Align8;
+
+pub const ROM_BASE: u32 = 0x1000_0000;
+pub const HEADER_SIZE: usize = 0x40;
+pub const IPL3_SIZE: usize = 0x1000 - HEADER_SIZE;
+
+/// the values libultra uses for `osTvType`
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TvType {
+    Pal = 0,
+    Ntsc = 1,
+    Mpal = 2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RomHeader {
+    pub pi_config: u32,
+    pub clock_rate: u32,
+    pub entrypoint: u32,
+    pub release: u32,
+    pub crc1: u32,
+    pub crc2: u32,
+    /// space-padded ASCII, or Shift-JIS in some Japanese games
+    pub title: [u8; 20],
+    /// media type, two-character game ID and region, e.g. `NSME`
+    pub game_code: [u8; 4],
+    pub version: u8,
+}
+
+impl RomHeader {
+    pub fn parse(data: &[u8; HEADER_SIZE]) -> Self {
+        let word = |i: usize| u32::from_be_bytes(data[i..i + 4].try_into().unwrap());
+
+        Self {
+            pi_config: word(0x00),
+            clock_rate: word(0x04),
+            entrypoint: word(0x08),
+            release: word(0x0C),
+            crc1: word(0x10),
+            crc2: word(0x14),
+            title: data[0x20..0x34].try_into().unwrap(),
+            game_code: data[0x3B..0x3F].try_into().unwrap(),
+            version: data[0x3F],
+        }
+    }
+
+    pub fn read(pi: &mut Pi) -> Self {
+        let mut buf = Align8([0; HEADER_SIZE]);
+
+        pi.read_into(&mut buf, ROM_BASE);
+
+        Self::parse(&buf.0)
+    }
+
+    pub fn timing(&self) -> DomainTiming {
+        DomainTiming::from_header_word(self.pi_config)
+    }
+
+    pub fn region(&self) -> u8 {
+        self.game_code[3]
+    }
+
+    pub fn tv_type(&self) -> TvType {
+        match self.region() {
+            b'D' | b'F' | b'H' | b'I' | b'L' | b'P' | b'S' | b'U' | b'W' | b'X' | b'Y' => {
+                TvType::Pal
+            }
+            b'B' => TvType::Mpal,
+            _ => TvType::Ntsc,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CicType {
+    Cic6101,
+    /// also 7101
+    Cic6102,
+    /// also 7103
+    Cic6103,
+    /// also 7105
+    Cic6105,
+    /// also 7106
+    Cic6106,
+    Cic7102,
+    /// 64DD retail IPL
+    Cic8303,
+    /// the CRC32 of an IPL3 we don't recognise
+    Unknown(u32),
+}
+
+impl CicType {
+    pub fn from_ipl3_crc(crc: u32) -> Self {
+        match crc {
+            0x6170_A4A1 => Self::Cic6101,
+            0x90BB_6CB5 => Self::Cic6102,
+            0x0B05_0EE0 => Self::Cic6103,
+            0x98BC_2C86 => Self::Cic6105,
+            0xACC8_580A => Self::Cic6106,
+            0x009E_9EA3 => Self::Cic7102,
+            0x0E01_8159 => Self::Cic8303,
+            _ => Self::Unknown(crc),
+        }
+    }
+
+    /// identifies the CIC from the IPL3 in the cartridge, which has to match it for the console
+    /// to have booted
+    pub fn detect(pi: &mut Pi) -> Self {
+        let mut buf = Align8([0u8; 0x100]);
+        let mut crc = !0;
+
+        // 0xFC0 isn't a multiple of the buffer, so the last chunk is partly read again and skipped
+        for offset in (0..IPL3_SIZE).step_by(buf.0.len()) {
+            let start = (IPL3_SIZE - buf.0.len()).min(offset);
+
+            pi.read_into(&mut buf, ROM_BASE + (HEADER_SIZE + start) as u32);
+
+            crc = crc32_update(crc, &buf.0[offset - start..]);
+        }
+
+        Self::from_ipl3_crc(!crc)
+    }
+
+    /// the part number, which is what ends up in `osCicId`
+    pub fn id(&self) -> u32 {
+        match self {
+            Self::Cic6101 => 6101,
+            Self::Cic6102 => 6102,
+            Self::Cic6103 => 6103,
+            Self::Cic6105 => 6105,
+            Self::Cic6106 => 6106,
+            Self::Cic7102 => 7102,
+            Self::Cic8303 => 8303,
+            Self::Unknown(_) => 0,
+        }
+    }
+}
+
+fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc ^= byte as u32;
+
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+
+    crc
+}
@@ -0,0 +1,201 @@
+use crate::joybus::{parse_single_response, JoybusCommand, RTC_CHANNEL};
+use crate::si::Si;
+
+const CONTROL_BLOCK: u8 = 0;
+const TIME_BLOCK: u8 = 2;
+
+// control block, byte 0: allow writes to blocks 1 and 2
+const CONTROL_UNLOCK_BLOCK1: u8 = 1 << 0;
+const CONTROL_UNLOCK_BLOCK2: u8 = 1 << 1;
+// control block, byte 1: stop the clock so the time can be set
+const CONTROL_STOP: u8 = 1 << 2;
+
+// must be set in the hours byte, otherwise the clock runs in 12-hour mode
+const HOURS_24: u8 = 0x80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RtcError {
+    NotPresent,
+    Transfer,
+    InvalidTime,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    /// 0 is Sunday
+    pub weekday: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+pub(crate) const fn to_bcd(val: u8) -> u8 {
+    ((val / 10) << 4) | (val % 10)
+}
+
+pub(crate) const fn from_bcd(val: u8) -> Option<u8> {
+    let (hi, lo) = (val >> 4, val & 0x0F);
+    if hi > 9 || lo > 9 {
+        None
+    } else {
+        Some(hi * 10 + lo)
+    }
+}
+
+impl DateTime {
+    pub const fn is_leap_year(&self) -> bool {
+        (self.year % 4 == 0 && self.year % 100 != 0) || self.year % 400 == 0
+    }
+
+    /// 0 if the month itself isn't valid
+    pub const fn days_in_month(&self) -> u8 {
+        match self.month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if self.is_leap_year() => 29,
+            2 => 28,
+            _ => 0,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        (1900..=2099).contains(&self.year)
+            && (1..=12).contains(&self.month)
+            && (1..=self.days_in_month()).contains(&self.day)
+            && self.weekday < 7
+            && self.hour < 24
+            && self.minute < 60
+            && self.second < 60
+    }
+
+    fn from_block(data: &[u8]) -> Result<Self, RtcError> {
+        let decode = |b| from_bcd(b).ok_or(RtcError::InvalidTime);
+
+        let century = data[7] as u16;
+
+        let time = Self {
+            year: 1900 + century * 100 + decode(data[6])? as u16,
+            month: decode(data[5])?,
+            weekday: decode(data[4])?,
+            day: decode(data[3])?,
+            hour: decode(data[2] & !HOURS_24)?,
+            minute: decode(data[1])?,
+            second: decode(data[0])?,
+        };
+
+        if time.is_valid() {
+            Ok(time)
+        } else {
+            Err(RtcError::InvalidTime)
+        }
+    }
+
+    fn to_block(self) -> [u8; 8] {
+        [
+            to_bcd(self.second),
+            to_bcd(self.minute),
+            to_bcd(self.hour) | HOURS_24,
+            to_bcd(self.day),
+            to_bcd(self.weekday),
+            to_bcd(self.month),
+            to_bcd((self.year % 100) as u8),
+            ((self.year - 1900) / 100) as u8,
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Rtc;
+
+impl Rtc {
+    pub fn detect(si: &mut Si) -> Option<Self> {
+        let cmd = JoybusCommand::RtcStatus;
+
+        let response = si.transact_single(RTC_CHANNEL, cmd);
+
+        let Ok(Some(data)) = parse_single_response(cmd, &response, RTC_CHANNEL) else {
+            return None;
+        };
+
+        // the same identifier bitfield the EEPROMs answer with, so it comes back as `00 10`
+        if u16::from_be_bytes([data[0], data[1]]) == 0x0010 {
+            Some(Self)
+        } else {
+            None
+        }
+    }
+
+    fn read_block(&self, si: &mut Si, block: u8) -> Result<[u8; 8], RtcError> {
+        let cmd = JoybusCommand::RtcRead(block);
+
+        let response = si.transact_single(RTC_CHANNEL, cmd);
+
+        match parse_single_response(cmd, &response, RTC_CHANNEL) {
+            // the last byte is the status, which we don't need
+            Ok(Some(data)) => Ok(data[..8].try_into().unwrap()),
+            Ok(None) => Err(RtcError::NotPresent),
+            Err(()) => Err(RtcError::Transfer),
+        }
+    }
+
+    fn write_block(&self, si: &mut Si, block: u8, data: [u8; 8]) -> Result<(), RtcError> {
+        let cmd = JoybusCommand::RtcWrite(block, data);
+
+        let response = si.transact_single(RTC_CHANNEL, cmd);
+
+        match parse_single_response(cmd, &response, RTC_CHANNEL) {
+            Ok(Some(_)) => Ok(()),
+            Ok(None) => Err(RtcError::NotPresent),
+            Err(()) => Err(RtcError::Transfer),
+        }
+    }
+
+    pub fn write_protected(&self, si: &mut Si) -> Result<bool, RtcError> {
+        let control = self.read_block(si, CONTROL_BLOCK)?;
+
+        Ok(control[0] & (CONTROL_UNLOCK_BLOCK1 | CONTROL_UNLOCK_BLOCK2) == 0)
+    }
+
+    /// protecting the clock also restarts it, in case it was left stopped
+    pub fn set_write_protect(&self, si: &mut Si, protect: bool) -> Result<(), RtcError> {
+        let control = if protect {
+            [0, 0, 0, 0, 0, 0, 0, 0]
+        } else {
+            [
+                CONTROL_UNLOCK_BLOCK1 | CONTROL_UNLOCK_BLOCK2,
+                CONTROL_STOP,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ]
+        };
+
+        self.write_block(si, CONTROL_BLOCK, control)
+    }
+
+    pub fn read(&self, si: &mut Si) -> Result<DateTime, RtcError> {
+        DateTime::from_block(&self.read_block(si, TIME_BLOCK)?)
+    }
+
+    /// the time block is only writable while write protection is off, so this lifts it for the
+    /// duration of the write and puts it back afterwards
+    pub fn set(&self, si: &mut Si, time: &DateTime) -> Result<(), RtcError> {
+        if !time.is_valid() {
+            return Err(RtcError::InvalidTime);
+        }
+
+        self.set_write_protect(si, false)?;
+
+        let result = self.write_block(si, TIME_BLOCK, time.to_block());
+
+        self.set_write_protect(si, true)?;
+
+        result
+    }
+}
@@ -1,5 +1,7 @@
 use core::{arch::asm, hint::black_box, mem::MaybeUninit, num::Wrapping};
 
+#[cfg(not(feature = "sk"))]
+use crate::boot::interrupts::im;
 use crate::types::Align8;
 use crate::{
     data_cache_invalidate, data_cache_writeback, io_ptr,
@@ -17,13 +19,41 @@ const SI_PIF_AD_RD4B: *mut u32 = io_ptr!(mut SI_BASE + 0x14);
 const SI_STATUS: *mut u32 = io_ptr!(mut SI_BASE + 0x18);
 const SI_CONFIG: *mut u32 = io_ptr!(mut SI_BASE + 0x0C);
 
-/*#[cfg(feature = "sk")]
+// the secure kernel runs from internal SRAM, which the SI can't DMA to or from, so its transfers
+// bounce through here
+#[cfg(feature = "sk")]
 #[link_section = ".dram"]
-pub static mut DRAM_BUF: [u8; 64] = unsafe { MaybeUninit::zeroed().assume_init() };*/
+static mut DRAM_BUF: Align8<[u8; 64]> = unsafe { MaybeUninit::zeroed().assume_init() };
+
+// asynchronous transfers are still running after the caller returns, so they need a buffer that
+// outlives it
+#[cfg(not(feature = "sk"))]
+static mut ASYNC_BUF: Align8<[u8; 64]> = Align8([0; 64]);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transfer {
+    Idle,
+    Write,
+    Read,
+    Complete,
+}
+
+/// identifies an asynchronous transfer started with `Si::start`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SiHandle(u32);
 
 pub struct Si {
     pub(crate) tx_index: Wrapping<u8>,
     pub(crate) rx_index: Wrapping<u8>,
+    transfer: Transfer,
+    sequence: u32,
+}
+
+#[cfg(not(feature = "sk"))]
+fn si_interrupt() -> bool {
+    si().handle_interrupt();
+    // already acknowledged, and acking again could lose the interrupt for the DMA just started
+    false
 }
 
 impl Si {
@@ -33,6 +63,8 @@ impl Si {
         Self {
             tx_index: Wrapping(0),
             rx_index: Wrapping(0),
+            transfer: Transfer::Idle,
+            sequence: 0,
         }
     }
 
@@ -40,6 +72,94 @@ impl Si {
         while self.status() & 3 != 0 {}
     }
 
+    // updated from the interrupt handler, so it mustn't be cached in a register
+    fn transfer(&self) -> Transfer {
+        unsafe { (&raw const self.transfer).read_volatile() }
+    }
+
+    pub fn is_busy(&self) -> bool {
+        matches!(self.transfer(), Transfer::Write | Transfer::Read)
+    }
+
+    pub fn wait_idle(&self) {
+        while self.is_busy() {}
+    }
+
+    /// installs the SI interrupt handler which drives transfers started with `start`
+    #[cfg(not(feature = "sk"))]
+    pub fn enable_async(&mut self) {
+        let im = im();
+        im.set_si_fn(Some(si_interrupt));
+        im.set_si(true);
+    }
+
+    /// starts sending `packet` to the PIF and reading back its response, without waiting for
+    /// either; returns `None` if another asynchronous transfer is still running
+    ///
+    /// `enable_async` must have been called, otherwise the transfer never completes
+    #[cfg(not(feature = "sk"))]
+    pub fn start(&mut self, packet: &[u8; 64]) -> Option<SiHandle> {
+        if self.is_busy() {
+            return None;
+        }
+
+        let buf = unsafe { &mut *(&raw mut ASYNC_BUF) };
+        buf.0 = *packet;
+
+        data_cache_writeback(&buf.0);
+
+        self.wait();
+
+        self.sequence = self.sequence.wrapping_add(1);
+        self.transfer = Transfer::Write;
+
+        self.set_dram_addr(k0_to_phys(buf.0.as_ptr()).addr() as _);
+        self.set_pif_ad_wr64b(Self::PIF_RAM_START);
+
+        Some(SiHandle(self.sequence))
+    }
+
+    /// returns the PIF's response once the transfer identified by `handle` has finished
+    ///
+    /// the response can only be collected once, after which the interface is free for the next
+    /// transfer
+    #[cfg(not(feature = "sk"))]
+    pub fn poll(&mut self, handle: SiHandle) -> Option<[u8; 64]> {
+        if handle.0 != self.sequence || self.transfer() != Transfer::Complete {
+            return None;
+        }
+
+        self.transfer = Transfer::Idle;
+
+        Some(unsafe { (*(&raw const ASYNC_BUF)).0 })
+    }
+
+    #[cfg(not(feature = "sk"))]
+    fn handle_interrupt(&mut self) {
+        // ack before starting the next DMA, so its completion can't be lost
+        self.set_status(0);
+
+        let buf = unsafe { &mut *(&raw mut ASYNC_BUF) };
+
+        match self.transfer() {
+            Transfer::Write => {
+                data_cache_invalidate(&buf.0);
+
+                self.transfer = Transfer::Read;
+
+                self.set_dram_addr(k0_to_phys_mut(buf.0.as_mut_ptr()).addr() as _);
+                self.set_pif_ad_rd64b(Self::PIF_RAM_START);
+            }
+            Transfer::Read => {
+                data_cache_invalidate(&buf.0);
+
+                self.transfer = Transfer::Complete;
+            }
+            // blocking transfers interrupt too, if the interrupt is enabled
+            Transfer::Idle | Transfer::Complete => {}
+        }
+    }
+
     pub fn init_hw(&mut self) {
         self.set_ctrl(0);
         self.set_config((self.config() & !0x7F000000) | (47 << 24) | (1 << 22));
@@ -49,6 +169,8 @@ impl Si {
     #[cfg(not(feature = "sk"))]
     #[track_caller]
     pub fn write(&mut self, data: &Align8<[u8; 64]>) {
+        self.wait_idle();
+
         data_cache_writeback(&data.0);
 
         let addr = data.0.as_ptr().addr();
@@ -66,61 +188,20 @@ impl Si {
         self.wait();
     }
 
-    /*#[cfg(feature = "sk")]
-    #[inline(never)]
-    pub fn write(&mut self) {
-        unsafe {
-            asm!(
-                ".set noreorder                 ",
-                ".set noat                      ",
-                "                               ",
-                "   la {t0}, {buf}              ",
-                "   cache 0x19, 0x00({t0})      ",
-                "   cache 0x19, 0x10({t0})      ",
-                "   cache 0x19, 0x20({t0})      ",
-                "   cache 0x19, 0x30({t0})      ",
-                "                               ",
-                "1:                             ",
-                "   lw {t0}, 0({status})        ",
-                "   and {t0}, 3                 ",
-                "   bnez {t0}, 1b               ",
-                "    nop                        ",
-                "                               ",
-                "   li {t0}, 0x1FFFFFFF         ",
-                "   la {t1}, {buf}              ",
-                "   and {t0}, {t0}, {t1}        ",
-                "   sw {t0}, 0({dram_addr})     ",
-                "                               ",
-                "   li {t0}, {pif_ram_start}    ",
-                "   sw {t0}, 0({pif_ad_wr64b})  ",
-                "                               ",
-                "1:                             ",
-                "   lw {t0}, 0({status})        ",
-                "   and {t0}, 3                 ",
-                "   bnez {t0}, 1b               ",
-                "    nop                        ",
-                "                               ",
-                ".set at                        ",
-                ".set reorder                   ",
-                t0 = out(reg) _,
-                buf = sym DRAM_BUF,
-                status = in(reg) SI_STATUS,
-                t1 = out(reg) _,
-                dram_addr = in(reg) SI_DRAM_ADDR,
-                pif_ram_start = const Self::PIF_RAM_START,
-                pif_ad_wr64b = in(reg) SI_PIF_AD_WR64B,
-                options(nostack)
-            )
-        }
-        /*data_cache_writeback(&unsafe { DRAM_BUF });
+    #[cfg(feature = "sk")]
+    pub fn write(&mut self, data: &Align8<[u8; 64]>) {
+        let buf = unsafe { &mut *(&raw mut DRAM_BUF) };
+        buf.0 = data.0;
+
+        data_cache_writeback(&buf.0);
 
         self.wait();
 
-        self.set_dram_addr(0);
+        self.set_dram_addr(k0_to_phys(buf.0.as_ptr()).addr() as _);
         self.set_pif_ad_wr64b(Self::PIF_RAM_START);
 
-        self.wait();*/
-    }*/
+        self.wait();
+    }
 
     #[cfg(not(feature = "sk"))]
     #[track_caller]
@@ -148,62 +229,23 @@ impl Si {
         buf.0
     }
 
-    /*#[cfg(feature = "sk")]
-    pub fn read(&mut self) {
-        unsafe {
-            asm!(
-                ".set noreorder                 ",
-                ".set noat                      ",
-                "                               ",
-                "1:                             ",
-                "   lw {t0}, 0({status})        ",
-                "   and {t0}, 3                 ",
-                "   bnez {t0}, 1b               ",
-                "    nop                        ",
-                "                               ",
-                "   li {t0}, 0x1FFFFFFF         ",
-                "   la {t1}, {buf}              ",
-                "   and {t0}, {t0}, {t1}        ",
-                "   sw {t0}, 0({dram_addr})     ",
-                "                               ",
-                "   li {t0}, {pif_ram_start}    ",
-                "   sw {t0}, 0({pif_ad_rd64b})  ",
-                "                               ",
-                "1:                             ",
-                "   lw {t0}, 0({status})        ",
-                "   and {t0}, 3                 ",
-                "   bnez {t0}, 1b               ",
-                "    nop                        ",
-                "                               ",
-                "   la {t0}, {buf}              ",
-                "   cache 0x11, 0x00({t0})      ",
-                "   cache 0x11, 0x10({t0})      ",
-                "   cache 0x11, 0x20({t0})      ",
-                "   cache 0x11, 0x30({t0})      ",
-                "                               ",
-                ".set at                        ",
-                ".set reorder                   ",
-                status = in(reg) SI_STATUS,
-                t0 = out(reg) _,
-                t1 = out(reg) _,
-                buf = sym DRAM_BUF,
-                dram_addr = in(reg) SI_DRAM_ADDR,
-                pif_ram_start = const Self::PIF_RAM_START,
-                pif_ad_rd64b = in(reg) SI_PIF_AD_RD64B,
-                options(nostack)
-            )
-        }
-        //data_cache_writeback(&buf);
+    #[cfg(feature = "sk")]
+    pub fn read(&mut self) -> [u8; 64] {
+        let buf = unsafe { &mut *(&raw mut DRAM_BUF) };
+
+        data_cache_invalidate(&buf.0);
 
-        /*self.wait();
+        self.wait();
 
-        self.set_dram_addr(0);
+        self.set_dram_addr(k0_to_phys_mut(buf.0.as_mut_ptr()).addr() as _);
         self.set_pif_ad_rd64b(Self::PIF_RAM_START);
 
         self.wait();
 
-        data_cache_invalidate(&unsafe { DRAM_BUF });*/
-    }*/
+        data_cache_invalidate(&buf.0);
+
+        buf.0
+    }
 
     pub fn dram_addr(&self) -> u32 {
         unsafe { SI_DRAM_ADDR.read_volatile() }
@@ -0,0 +1,148 @@
+use core::ptr::from_raw_parts_mut;
+
+use crate::pi::{Domain, DomainTiming, Pi};
+use crate::types::Align8;
+
+const SRAM_BASE: u32 = 0x0800_0000;
+
+pub const BANK_SIZE: usize = 0x8000;
+
+// the bank is selected with the address bits just above the PI's 18-bit SRAM window
+const BANK_SHIFT: u32 = 18;
+
+// DMAs go through here so callers can use any buffer, at any offset
+const CHUNK_SIZE: usize = 0x100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SramType {
+    Sram32K,
+    /// three 32 KiB banks, as used by Dezaemon 3D
+    Sram96K,
+}
+
+impl SramType {
+    pub const fn banks(self) -> usize {
+        match self {
+            Self::Sram32K => 1,
+            Self::Sram96K => 3,
+        }
+    }
+
+    pub const fn size(self) -> usize {
+        self.banks() * BANK_SIZE
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SramError {
+    OutOfRange,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Sram {
+    sram_type: SramType,
+}
+
+impl Sram {
+    /// there's no way to ask the cartridge what it has, so the caller has to know
+    pub fn new(pi: &mut Pi, sram_type: SramType) -> Self {
+        pi.set_domain_timing(Domain::Dom2, DomainTiming::SRAM);
+
+        Self { sram_type }
+    }
+
+    pub fn sram_type(&self) -> SramType {
+        self.sram_type
+    }
+
+    fn check_range(&self, offset: usize, len: usize) -> Result<(), SramError> {
+        if offset + len > self.sram_type.size() {
+            Err(SramError::OutOfRange)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn cart_addr(offset: usize) -> u32 {
+        let bank = (offset / BANK_SIZE) as u32;
+
+        SRAM_BASE + (bank << BANK_SHIFT) + (offset % BANK_SIZE) as u32
+    }
+
+    /// splits `offset..offset + len` into pieces which fit in the bounce buffer and don't cross a
+    /// bank, calling `f` with each piece's bounds and its position in the caller's buffer
+    fn for_each_chunk(offset: usize, len: usize, mut f: impl FnMut(usize, usize, usize)) {
+        let mut done = 0;
+
+        while done < len {
+            let start = offset + done;
+            let bank_end = (start / BANK_SIZE + 1) * BANK_SIZE;
+            let end = ((start & !1) + CHUNK_SIZE).min(offset + len).min(bank_end);
+
+            f(start, end, done);
+
+            done += end - start;
+        }
+    }
+
+    // the PI only moves whole halfwords
+    fn align(start: usize, end: usize) -> (usize, usize) {
+        (start & !1, (end + 1) & !1)
+    }
+
+    fn bounce(buf: &mut Align8<[u8; CHUNK_SIZE]>, len: usize) -> &mut Align8<[u8]> {
+        unsafe { &mut *from_raw_parts_mut::<Align8<[u8]>>(buf.0.as_mut_ptr() as *mut (), len) }
+    }
+
+    pub fn read(&self, pi: &mut Pi, offset: usize, data: &mut [u8]) -> Result<(), SramError> {
+        self.check_range(offset, data.len())?;
+
+        let mut buf = Align8([0; CHUNK_SIZE]);
+
+        Self::for_each_chunk(offset, data.len(), |start, end, pos| {
+            let (aligned_start, aligned_end) = Self::align(start, end);
+
+            pi.read_into(
+                Self::bounce(&mut buf, aligned_end - aligned_start),
+                Self::cart_addr(aligned_start),
+            );
+
+            let skip = start - aligned_start;
+            let len = end - start;
+
+            data[pos..pos + len].copy_from_slice(&buf.0[skip..skip + len]);
+        });
+
+        Ok(())
+    }
+
+    pub fn write(&self, pi: &mut Pi, offset: usize, data: &[u8]) -> Result<(), SramError> {
+        self.check_range(offset, data.len())?;
+
+        let mut buf = Align8([0; CHUNK_SIZE]);
+
+        Self::for_each_chunk(offset, data.len(), |start, end, pos| {
+            let (aligned_start, aligned_end) = Self::align(start, end);
+            let addr = Self::cart_addr(aligned_start);
+            let bounce_len = aligned_end - aligned_start;
+
+            let skip = start - aligned_start;
+            let len = end - start;
+
+            // keep the neighbouring bytes of any halfword we only partly cover
+            if skip != 0 || skip + len != bounce_len {
+                pi.read_into(Self::bounce(&mut buf, bounce_len), addr);
+            }
+
+            buf.0[skip..skip + len].copy_from_slice(&data[pos..pos + len]);
+
+            pi.write(Self::bounce(&mut buf, bounce_len), addr);
+        });
+
+        Ok(())
+    }
+
+    pub fn read_all(&self, pi: &mut Pi, data: &mut [u8]) -> Result<(), SramError> {
+        self.read(pi, 0, data)
+    }
+}
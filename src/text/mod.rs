@@ -19,6 +19,18 @@ impl From<Colour> for u32 {
     }
 }
 
+impl From<u16> for Colour {
+    fn from(value: u16) -> Self {
+        Self::from_rgba5551(value)
+    }
+}
+
+impl From<Colour> for u16 {
+    fn from(val: Colour) -> Self {
+        val.to_rgba5551()
+    }
+}
+
 /*macro_rules! define_colours {
     ($($name:ident, $r:expr, $g:expr, $b:expr, $a:expr),*) => {
         enum Colour {
@@ -46,15 +58,29 @@ macro_rules! define_colour {
 
 impl Colour {
     pub const fn from_rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
-        /*Self(
-            ((r * 31 / 255) as u16) << 11
-                | ((g * 31 / 255) as u16) << 6
-                | ((b * 31 / 255) as u16) << 1
-                | ((a * 1 / 255) as u16),
-        )*/
         Self(((r as u32) << 24) | ((g as u32) << 16) | ((b as u32) << 8) | ((a as u32) << 0))
     }
 
+    pub const fn from_rgba5551(val: u16) -> Self {
+        // copy the top bits into the bottom, so full intensity stays 0xFF
+        const fn expand(c: u16) -> u8 {
+            ((c << 3) | (c >> 2)) as u8
+        }
+
+        Self::from_rgba(
+            expand((val >> 11) & 0x1F),
+            expand((val >> 6) & 0x1F),
+            expand((val >> 1) & 0x1F),
+            if val & 1 != 0 { 0xFF } else { 0 },
+        )
+    }
+
+    pub const fn to_rgba5551(self) -> u16 {
+        let [r, g, b, a] = self.0.to_be_bytes();
+
+        ((r as u16 >> 3) << 11) | ((g as u16 >> 3) << 6) | ((b as u16 >> 3) << 1) | (a as u16 >> 7)
+    }
+
     define_colour!(WHITE, 0xFF, 0xFF, 0xFF, 0xFF);
     define_colour!(BLACK, 0, 0, 0, 0);
     define_colour!(GREY, 0x7F, 0x7F, 0x7F, 0xFF);
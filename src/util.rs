@@ -20,6 +20,10 @@ pub const fn k0_to_phys_usize(addr: usize) -> usize {
     addr & !0xE0000000
 }
 
+pub const fn phys_to_k0_usize(addr: usize) -> usize {
+    addr | 0x80000000
+}
+
 pub const fn phys_to_k1_usize(addr: usize) -> usize {
     addr | 0xA0000000
 }
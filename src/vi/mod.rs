@@ -1,9 +1,15 @@
 use core::ops::{Index, IndexMut};
 use core::ptr::from_raw_parts_mut;
 
+#[cfg(not(feature = "sk"))]
+use crate::boot::globals::osTvType;
 use crate::boot::is_bbplayer;
 use crate::cop0::cop0;
 use crate::mi::mi;
+#[cfg(not(feature = "sk"))]
+use crate::pi::pi;
+#[cfg(not(feature = "sk"))]
+use crate::rom::{RomHeader, TvType};
 use crate::util::phys_to_k1_u32;
 use crate::{data_cache_writeback, io_ptr};
 
@@ -61,6 +67,17 @@ pub struct Vi {
 pub const WIDTH: usize = 320;
 pub const HEIGHT: usize = 240;
 
+/// a framebuffer pixel, RGBA5551 with the `rgba5551` feature and RGBA8888 otherwise
+#[cfg(feature = "rgba5551")]
+pub type Pixel = u16;
+#[cfg(not(feature = "rgba5551"))]
+pub type Pixel = u32;
+
+#[cfg(feature = "rgba5551")]
+const PIXEL_SIZE: PixelSize = PixelSize::Rgba5553;
+#[cfg(not(feature = "rgba5551"))]
+const PIXEL_SIZE: PixelSize = PixelSize::Rgba8;
+
 #[repr(align(8))]
 struct Framebuffer<const W: usize, const H: usize, const N: usize, T>([[T; W * H]; N])
 where
@@ -88,13 +105,44 @@ where
 }
 
 #[cfg_attr(feature = "sk_vi", link_section = ".dram")]
-static mut FRAMEBUFFER: Framebuffer<WIDTH, HEIGHT, 2, u32> =
+static mut FRAMEBUFFER: Framebuffer<WIDTH, HEIGHT, 2, Pixel> =
     unsafe { core::mem::MaybeUninit::zeroed().assume_init() };
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Mode {
     PAL50,
     PAL60,
     NTSC,
+    MPAL,
+}
+
+// the BB's video PLL, in MI 0x30; there's no PAL setting since none has been checked on hardware
+const AVCTRL_NTSC: u32 = 0x194244;
+
+impl Mode {
+    /// the mode for the console's TV standard, from `osTvType` or failing that the ROM's region
+    #[cfg(not(feature = "sk"))]
+    pub fn detect() -> Self {
+        let tv_type = match unsafe { (&raw const osTvType).read_volatile() } {
+            0 => TvType::Pal,
+            1 => TvType::Ntsc,
+            2 => TvType::Mpal,
+            _ => RomHeader::read(pi()).tv_type(),
+        };
+
+        tv_type.into()
+    }
+}
+
+#[cfg(not(feature = "sk"))]
+impl From<TvType> for Mode {
+    fn from(tv_type: TvType) -> Self {
+        match tv_type {
+            TvType::Pal => Self::PAL50,
+            TvType::Ntsc => Self::NTSC,
+            TvType::Mpal => Self::MPAL,
+        }
+    }
 }
 
 impl Vi {
@@ -106,7 +154,7 @@ impl Vi {
     }
 
     #[cfg(any(not(feature = "sk"), feature = "sk_vi"))]
-    pub fn get_next_framebuffer(&self) -> &'static mut [u32; WIDTH * HEIGHT] {
+    pub fn get_next_framebuffer(&self) -> &'static mut [Pixel; WIDTH * HEIGHT] {
         // godawful codegen on this for some reason
         unsafe { &mut FRAMEBUFFER[self.next_framebuffer as usize] }
     }
@@ -354,7 +402,7 @@ impl Vi {
                     | Ctrl::divot_enable(false)
                     | Ctrl::gamma_enable(true)
                     | Ctrl::gamma_dither_enable(true)
-                    | Ctrl::pixel_size(PixelSize::Rgba8),
+                    | Ctrl::pixel_size(PIXEL_SIZE),
             );
 
             while self.v_current() < 48 {}
@@ -382,7 +430,7 @@ impl Vi {
                 | Ctrl::divot_enable(false)
                 | Ctrl::gamma_enable(true)
                 | Ctrl::gamma_dither_enable(true)
-                | Ctrl::pixel_size(PixelSize::Rgba8),
+                | Ctrl::pixel_size(PIXEL_SIZE),
         );
 
         self.set_origin(self.get_next_framebuffer().as_ptr().addr() as _);
@@ -390,7 +438,20 @@ impl Vi {
         self.set_v_intr(2);
 
         match mode {
-            Mode::PAL50 => todo!(),
+            Mode::PAL50 => {
+                self.set_burst(
+                    Burst::burst_start(64)
+                        | Burst::vsync_width(4)
+                        | Burst::burst_width(35)
+                        | Burst::hsync_width(58),
+                );
+                self.set_v_sync(625);
+                self.set_h_sync(HSync::leap(21) | HSync::h_sync(3177));
+                self.set_h_sync_leap(HSyncLeap::leap_a(3183) | HSyncLeap::leap_b(3182));
+                self.set_h_video(Video::start(128) | Video::end(768));
+                self.set_v_video(Video::start(95) | Video::end(569));
+                self.set_v_burst(Video::start(9) | Video::end(619));
+            }
             Mode::PAL60 => {
                 self.set_burst(
                     Burst::burst_start(69)
@@ -402,6 +463,8 @@ impl Vi {
                 self.set_h_sync(HSync::leap(23) | HSync::h_sync(3177));
                 self.set_h_sync_leap(HSyncLeap::leap_a(3183) | HSyncLeap::leap_b(3181));
                 self.set_h_video(Video::start(128) | Video::end(768));
+                self.set_v_video(Video::start(37) | Video::end(511));
+                self.set_v_burst(Video::start(14) | Video::end(516));
             }
             Mode::NTSC => {
                 self.set_burst(
@@ -414,11 +477,25 @@ impl Vi {
                 self.set_h_sync(HSync::leap(0) | HSync::h_sync(3093));
                 self.set_h_sync_leap(HSyncLeap::leap_a(3093) | HSyncLeap::leap_b(3093));
                 self.set_h_video(Video::start(108) | Video::end(748));
+                self.set_v_video(Video::start(37) | Video::end(511));
+                self.set_v_burst(Video::start(14) | Video::end(516));
+            }
+            Mode::MPAL => {
+                self.set_burst(
+                    Burst::burst_start(70)
+                        | Burst::vsync_width(5)
+                        | Burst::burst_width(30)
+                        | Burst::hsync_width(57),
+                );
+                self.set_v_sync(525);
+                self.set_h_sync(HSync::leap(4) | HSync::h_sync(3089));
+                self.set_h_sync_leap(HSyncLeap::leap_a(3097) | HSyncLeap::leap_b(3098));
+                self.set_h_video(Video::start(108) | Video::end(748));
+                self.set_v_video(Video::start(37) | Video::end(511));
+                self.set_v_burst(Video::start(14) | Video::end(516));
             }
         }
 
-        self.set_v_video(Video::start(37) | Video::end(511));
-        self.set_v_burst(Video::start(14) | Video::end(516));
         self.set_x_scale(
             Scale::offset(FixedPoint::new(0, 0)) | Scale::scale(FixedPoint::new(0, 512)),
         );
@@ -429,6 +506,17 @@ impl Vi {
         self.next_framebuffer();
     }
 
+    /// initialises for the console's TV standard, see `Mode::detect`
+    #[cfg(not(feature = "sk"))]
+    pub fn init_auto(&mut self) {
+        self.init(Mode::detect());
+    }
+
+    /// sets up the BB's video PLL for NTSC, whatever mode the VI is in
+    ///
+    /// PAL isn't supported here yet: its timings still run on top of the NTSC clock, but the line
+    /// rate and colour subcarrier come out wrong until a PAL avctrl value has been worked out and
+    /// checked on hardware
     #[cfg(any(not(feature = "sk"), feature = "sk_vi"))]
     pub fn pll_init(&mut self) {
         let cop0 = cop0();
@@ -454,7 +542,7 @@ impl Vi {
 
         cop0.delay(50);
 
-        let avctrl = 0x194244; // NTSC
+        let avctrl = AVCTRL_NTSC;
 
         mi.set_unknown(0x30, avctrl | (1 << 23) | (1 << 0));
         mi.unknown(0x30);
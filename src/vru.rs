@@ -0,0 +1,240 @@
+use crate::joybus::{data_crc, parse_single_response, JoybusCommand};
+use crate::si::Si;
+
+pub const MAX_CANDIDATES: usize = 5;
+pub const MAX_WORDS: u16 = 256;
+/// words are given in the VRU's own encoding (Shift-JIS hiragana), two bytes per character
+pub const MAX_WORD_LEN: usize = 40;
+
+const CHUNK_SIZE: usize = 20;
+
+// sent to the A/D converter, one byte at a time, to bring it up
+const INIT_SEQUENCE: [u8; 5] = [0x1E, 0x6E, 0x08, 0x56, 0x03];
+
+// libultra's osVoice* functions send every read and write to address 0; the VRU tells status,
+// config, results and words apart by the command and the order they arrive in
+const VRU_ADDR: u16 = 0x0000;
+
+const CONFIG_CLEAR_DICTIONARY: u8 = 0x02;
+const CONFIG_REGISTER_WORD: u8 = 0x03;
+const CONFIG_START: u8 = 0x05;
+const CONFIG_STOP: u8 = 0x00;
+
+const STATUS_BUSY: u16 = 1 << 0;
+const STATUS_RESULT_READY: u16 = 1 << 2;
+const STATUS_CONVERTER_OFF: u16 = 1 << 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VruError {
+    NotPresent,
+    Transfer,
+    Crc,
+    NoResponse,
+    DictionaryFull,
+    InvalidWord,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct VruResult {
+    pub warning: u16,
+    pub answer_count: u16,
+    pub voice_level: u16,
+    pub relative_level: u16,
+    pub voice_time: u16,
+    /// indices of the best matching registered words, best first
+    pub answers: [u16; MAX_CANDIDATES],
+    /// how far each answer is from what was heard; lower is better
+    pub distances: [u16; MAX_CANDIDATES],
+}
+
+impl VruResult {
+    fn parse(data: &[u8]) -> Self {
+        let word = |i: usize| u16::from_be_bytes([data[i * 2], data[i * 2 + 1]]);
+
+        Self {
+            warning: word(0),
+            answer_count: word(1),
+            voice_level: word(2),
+            relative_level: word(3),
+            voice_time: word(4),
+            answers: core::array::from_fn(|i| word(5 + i)),
+            distances: core::array::from_fn(|i| word(5 + MAX_CANDIDATES + i)),
+        }
+    }
+
+    /// the best match, if the VRU was confident enough to give one
+    pub fn best(&self) -> Option<u16> {
+        if self.answer_count == 0 {
+            None
+        } else {
+            Some(self.answers[0])
+        }
+    }
+}
+
+/// the Voice Recognition Unit, which matches speech against a dictionary of registered words
+pub struct Vru {
+    port: usize,
+    words: u16,
+    listening: bool,
+}
+
+impl Vru {
+    /// `N` has to match the response size of `cmd`
+    fn transact<const N: usize>(
+        &self,
+        si: &mut Si,
+        cmd: JoybusCommand,
+    ) -> Result<[u8; N], VruError> {
+        let response = si.transact_single(self.port, cmd);
+
+        match parse_single_response(cmd, &response, self.port) {
+            Ok(Some(data)) => Ok(data.try_into().unwrap()),
+            Ok(None) => Err(VruError::NotPresent),
+            Err(()) => Err(VruError::Transfer),
+        }
+    }
+
+    fn status(&self, si: &mut Si) -> Result<u16, VruError> {
+        let [hi, lo, _] = self.transact(si, JoybusCommand::VruStatus(VRU_ADDR))?;
+
+        Ok(u16::from_be_bytes([hi, lo]))
+    }
+
+    fn write_init(&self, si: &mut Si, data: u8) -> Result<(), VruError> {
+        let [crc] = self.transact(si, JoybusCommand::VruInit(data))?;
+
+        if crc != data_crc(&[data]) {
+            return Err(VruError::Crc);
+        }
+
+        Ok(())
+    }
+
+    fn write_config(&self, si: &mut Si, config: [u8; 4]) -> Result<(), VruError> {
+        let [crc] = self.transact(si, JoybusCommand::VruConfig(VRU_ADDR, config))?;
+
+        if crc != data_crc(&config) {
+            return Err(VruError::Crc);
+        }
+
+        Ok(())
+    }
+
+    fn write(&self, si: &mut Si, addr: u16, data: [u8; CHUNK_SIZE]) -> Result<(), VruError> {
+        let [crc] = self.transact(si, JoybusCommand::VruWrite(addr, data))?;
+
+        if crc != data_crc(&data) {
+            return Err(VruError::Crc);
+        }
+
+        Ok(())
+    }
+
+    fn read(&self, si: &mut Si, addr: u16) -> Result<[u8; 36], VruError> {
+        let response: [u8; 37] = self.transact(si, JoybusCommand::VruRead(addr))?;
+
+        let (data, crc) = response.split_at(36);
+
+        if crc[0] != data_crc(data) {
+            return Err(VruError::Crc);
+        }
+
+        Ok(data.try_into().unwrap())
+    }
+
+    pub fn init(si: &mut Si, port: usize) -> Result<Self, VruError> {
+        let mut vru = Self {
+            port,
+            words: 0,
+            listening: false,
+        };
+
+        if vru.status(si)? & STATUS_CONVERTER_OFF != 0 {
+            vru.write_init(si, 0)?;
+
+            if vru.status(si)? & STATUS_CONVERTER_OFF != 0 {
+                return Err(VruError::NoResponse);
+            }
+        }
+
+        for data in INIT_SEQUENCE {
+            vru.write_init(si, data)?;
+        }
+
+        vru.clear_dictionary(si)?;
+
+        Ok(vru)
+    }
+
+    pub fn words(&self) -> u16 {
+        self.words
+    }
+
+    pub fn clear_dictionary(&mut self, si: &mut Si) -> Result<(), VruError> {
+        self.write_config(si, [0, 0, CONFIG_CLEAR_DICTIONARY, 0])?;
+        self.words = 0;
+
+        Ok(())
+    }
+
+    /// adds a word to the dictionary, returning the index recognition results will refer to it by
+    pub fn register_word(&mut self, si: &mut Si, word: &[u8]) -> Result<u16, VruError> {
+        if word.is_empty() || word.len() > MAX_WORD_LEN || word.len() % 2 != 0 {
+            return Err(VruError::InvalidWord);
+        }
+
+        if self.words >= MAX_WORDS {
+            return Err(VruError::DictionaryFull);
+        }
+
+        let mut buf = [0; MAX_WORD_LEN];
+        buf[..word.len()].copy_from_slice(word);
+
+        // the chunks go to the same address, one after the other
+        for chunk in buf.chunks_exact(CHUNK_SIZE) {
+            self.write(si, VRU_ADDR, chunk.try_into().unwrap())?;
+        }
+
+        let [hi, lo] = self.words.to_be_bytes();
+        self.write_config(si, [hi, lo, CONFIG_REGISTER_WORD, (word.len() / 2) as u8])?;
+
+        self.words += 1;
+
+        Ok(self.words - 1)
+    }
+
+    pub fn start_recognition(&mut self, si: &mut Si) -> Result<(), VruError> {
+        self.write_config(si, [0, 0, CONFIG_START, 0])?;
+        self.listening = true;
+
+        Ok(())
+    }
+
+    pub fn stop_recognition(&mut self, si: &mut Si) -> Result<(), VruError> {
+        self.write_config(si, [0, 0, CONFIG_STOP, 0])?;
+        self.listening = false;
+
+        Ok(())
+    }
+
+    /// returns `Ok(None)` while the VRU is still listening; once a result has been returned, the
+    /// VRU stops and `start_recognition` has to be called again
+    pub fn poll_result(&mut self, si: &mut Si) -> Result<Option<VruResult>, VruError> {
+        if !self.listening {
+            return Ok(None);
+        }
+
+        let status = self.status(si)?;
+
+        if status & STATUS_BUSY != 0 || status & STATUS_RESULT_READY == 0 {
+            return Ok(None);
+        }
+
+        let result = VruResult::parse(&self.read(si, VRU_ADDR)?);
+
+        self.listening = false;
+
+        Ok(Some(result))
+    }
+}
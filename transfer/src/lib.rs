@@ -0,0 +1,160 @@
+//! splits PI reads with arbitrary alignment and length into transfers the PI can actually do
+//!
+//! the PI only DMAs into 8-byte aligned RAM, from 2-byte aligned cartridge addresses, in
+//! multiples of 2 bytes; everything else goes through a small bounce buffer
+
+#![no_std]
+
+use core::ops::Range;
+
+pub const DRAM_ALIGN: usize = 8;
+pub const CART_ALIGN: u32 = 2;
+
+pub const BOUNCE_SIZE: usize = 0x80;
+
+// a bounce chunk which starts on an odd address needs an extra byte either side
+const BOUNCE_MAX: usize = BOUNCE_SIZE - 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// straight into the destination, at `offset` bytes in
+    Direct {
+        offset: usize,
+        cart: u32,
+        len: usize,
+    },
+    /// through the bounce buffer, then copied to `offset` bytes in
+    Bounce {
+        offset: usize,
+        cart: u32,
+        len: usize,
+    },
+}
+
+impl Step {
+    /// for a bounce step, the aligned range to DMA and where the wanted bytes start in it
+    pub fn window(&self) -> (u32, usize, usize) {
+        let Self::Bounce { cart, len, .. } = *self else {
+            panic!("only bounce steps need a window");
+        };
+
+        let skip = (cart % CART_ALIGN) as usize;
+
+        (
+            cart - skip as u32,
+            skip,
+            (skip + len).next_multiple_of(CART_ALIGN as usize),
+        )
+    }
+}
+
+/// the steps for reading `len` bytes from `cart` to `dst`, direct one first
+///
+/// the direct step goes first so the bounce copies around it can't be undone by the cache
+/// maintenance the direct DMA needs
+#[derive(Debug, Clone)]
+pub struct Plan {
+    cart: u32,
+    len: usize,
+    direct: Range<usize>,
+    direct_done: bool,
+    pos: usize,
+}
+
+pub fn plan(dst: usize, cart: u32, len: usize) -> Plan {
+    let head = dst.next_multiple_of(DRAM_ALIGN) - dst;
+
+    // the gap between RAM and cartridge alignment never changes along the transfer, so if it's
+    // wrong at the start there's no direct part at all
+    let direct = if head < len && (cart as usize + head) % CART_ALIGN as usize == 0 {
+        let direct_len = (len - head) & !(CART_ALIGN as usize - 1);
+
+        head..head + direct_len
+    } else {
+        0..0
+    };
+
+    Plan {
+        cart,
+        len,
+        direct_done: direct.is_empty(),
+        direct,
+        pos: 0,
+    }
+}
+
+impl Iterator for Plan {
+    type Item = Step;
+
+    fn next(&mut self) -> Option<Step> {
+        if !self.direct_done {
+            self.direct_done = true;
+
+            return Some(Step::Direct {
+                offset: self.direct.start,
+                cart: self.cart + self.direct.start as u32,
+                len: self.direct.len(),
+            });
+        }
+
+        if self.pos == self.direct.start && !self.direct.is_empty() {
+            self.pos = self.direct.end;
+        }
+
+        if self.pos >= self.len {
+            return None;
+        }
+
+        let end = if self.pos < self.direct.start {
+            self.direct.start
+        } else {
+            self.len
+        };
+
+        let len = (end - self.pos).min(BOUNCE_MAX);
+
+        let step = Step::Bounce {
+            offset: self.pos,
+            cart: self.cart + self.pos as u32,
+            len,
+        };
+
+        self.pos += len;
+
+        Some(step)
+    }
+}
+
+#[repr(C, align(8))]
+pub struct BounceBuffer(pub [u8; BOUNCE_SIZE]);
+
+/// something that can do aligned PI reads
+pub trait PiRead {
+    /// reads `len` bytes from `cart` to `dst`
+    ///
+    /// # Safety
+    ///
+    /// `dst` must be valid for `len` bytes and `DRAM_ALIGN`-aligned, `cart` must be
+    /// `CART_ALIGN`-aligned and `len` must be a multiple of `CART_ALIGN`
+    unsafe fn dma_read(&mut self, dst: *mut u8, cart: u32, len: usize);
+}
+
+/// reads `dst.len()` bytes from `cart`, whatever the alignment
+pub fn read(pi: &mut impl PiRead, dst: &mut [u8], cart: u32) {
+    let mut bounce = BounceBuffer([0; BOUNCE_SIZE]);
+
+    for step in plan(dst.as_ptr() as usize, cart, dst.len()) {
+        match step {
+            Step::Direct { offset, cart, len } => unsafe {
+                pi.dma_read(dst[offset..offset + len].as_mut_ptr(), cart, len)
+            },
+            Step::Bounce { offset, len, .. } => {
+                let (window_cart, skip, window_len) = step.window();
+
+                unsafe { pi.dma_read(bounce.0.as_mut_ptr(), window_cart, window_len) };
+
+                dst[offset..offset + len].copy_from_slice(&bounce.0[skip..skip + len]);
+            }
+        }
+    }
+}
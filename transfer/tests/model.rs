@@ -0,0 +1,93 @@
+use n64_transfer::{read, PiRead, Step, BOUNCE_SIZE, CART_ALIGN, DRAM_ALIGN};
+
+/// a cartridge which checks every DMA against the PI's rules
+struct Model {
+    rom: Vec<u8>,
+    dmas: usize,
+}
+
+impl PiRead for Model {
+    unsafe fn dma_read(&mut self, dst: *mut u8, cart: u32, len: usize) {
+        assert_eq!(dst as usize % DRAM_ALIGN, 0, "unaligned DRAM address");
+        assert_eq!(cart % CART_ALIGN, 0, "unaligned cartridge address");
+        assert_eq!(len % CART_ALIGN as usize, 0, "odd length");
+
+        let cart = cart as usize;
+        std::ptr::copy_nonoverlapping(self.rom[cart..cart + len].as_ptr(), dst, len);
+
+        self.dmas += 1;
+    }
+}
+
+fn rom() -> Vec<u8> {
+    (0..0x1000u32).map(|i| (i * 7 + (i >> 8)) as u8).collect()
+}
+
+#[repr(align(16))]
+struct Dst([u8; 0x400]);
+
+#[test]
+fn every_alignment() {
+    let mut model = Model {
+        rom: rom(),
+        dmas: 0,
+    };
+    let mut dst = Dst([0; 0x400]);
+
+    for dst_offset in 0..16 {
+        for cart in 0..8u32 {
+            for len in (0..0x40).chain([0xFE, 0xFF, 0x100, 0x101, 0x2FF]) {
+                dst.0.fill(0xEE);
+
+                read(&mut model, &mut dst.0[dst_offset..dst_offset + len], cart);
+
+                let expected = &model.rom[cart as usize..cart as usize + len];
+
+                assert_eq!(&dst.0[dst_offset..dst_offset + len], expected);
+
+                // and nothing around it was touched
+                assert!(dst.0[..dst_offset].iter().all(|&b| b == 0xEE));
+                assert!(dst.0[dst_offset + len..].iter().all(|&b| b == 0xEE));
+            }
+        }
+    }
+}
+
+#[test]
+fn aligned_is_one_dma() {
+    let mut model = Model {
+        rom: rom(),
+        dmas: 0,
+    };
+    let mut dst = Dst([0; 0x400]);
+
+    read(&mut model, &mut dst.0[..0x400], 0x100);
+
+    assert_eq!(model.dmas, 1);
+}
+
+#[test]
+fn direct_goes_first() {
+    let steps: Vec<_> = n64_transfer::plan(0x1003, 0x11, 0x200).collect();
+
+    assert!(matches!(steps[0], Step::Direct { .. }));
+    assert!(steps[1..].iter().all(|s| matches!(s, Step::Bounce { .. })));
+
+    let covered: usize = steps
+        .iter()
+        .map(|s| match *s {
+            Step::Direct { len, .. } | Step::Bounce { len, .. } => len,
+        })
+        .sum();
+
+    assert_eq!(covered, 0x200);
+}
+
+#[test]
+fn mismatched_parity_is_all_bounce() {
+    let steps: Vec<_> = n64_transfer::plan(0x1000, 0x11, 0x200).collect();
+
+    assert!(steps
+        .iter()
+        .all(|s| matches!(s, Step::Bounce { len, .. } if *len <= BOUNCE_SIZE)));
+}